@@ -0,0 +1,353 @@
+use utils::Dial;
+
+use crate::{Algorithm, Method, count, run};
+
+// xorshift64*, good enough to shuffle candidate moves
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng(seed ^ 0x9E37_79B9_7F4A_7C15 | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+// all moves from offset `from` as (smallest rotation, full turns that still fit)
+fn moves(from: i64, size: i64, max_magnitude: i64) -> Vec<(i64, i64)> {
+    let mut result = Vec::new();
+    for to in 0..size {
+        for direction in [1, -1] {
+            let mut distance = ((to - from) * direction).rem_euclid(size);
+            if distance == 0 {
+                distance = size;
+            }
+            if distance <= max_magnitude {
+                result.push((direction * distance, (max_magnitude - distance) / size));
+            }
+        }
+    }
+    result
+}
+
+fn contribution(dial: &Dial, rotation: i64, method: Method) -> u64 {
+    let mut dial = *dial;
+    let (_, passes) = dial.rotate(rotation);
+    count(method, passes, dial.is_on_target())
+}
+
+// range minimum or maximum over the values of a dial, read cyclically
+struct SparseTable {
+    levels: Vec<Vec<u64>>,
+    op: fn(u64, u64) -> u64,
+}
+
+impl SparseTable {
+    fn new(values: &[u64], op: fn(u64, u64) -> u64) -> SparseTable {
+        let mut levels = vec![[values, values].concat()];
+        let mut width = 1;
+        while width * 2 <= values.len() {
+            let last = levels.last().unwrap();
+            let level = (0..last.len() - width)
+                .map(|i| op(last[i], last[i + width]))
+                .collect();
+            levels.push(level);
+            width *= 2;
+        }
+        SparseTable { levels, op }
+    }
+
+    // the `len` values from offset `start` on, wrapping around the dial
+    fn query(&self, start: usize, len: usize) -> u64 {
+        let level = len.ilog2() as usize;
+        let values = &self.levels[level];
+        (self.op)(values[start], values[start + len - (1 << level)])
+    }
+}
+
+pub struct Generator {
+    dial: Dial,
+    method: Method,
+    max_magnitude: i64,
+    // lowest / highest password reachable with `steps` instructions from a dial offset
+    min: Vec<Vec<u64>>,
+    max: Vec<Vec<u64>>,
+}
+
+impl Generator {
+    /// Tabulates the reachable passwords in O(instructions * size * log size).
+    ///
+    /// Turning from one offset in one direction by 1..=size clicks, the
+    /// contribution only changes at the click that first reaches the target
+    /// and where one full turn fewer still fits into `max_magnitude`, so the
+    /// offsets in between are handled together with a range query.
+    ///
+    /// An instruction adds at most `max_magnitude` to the password, so every
+    /// password fits as long as `instructions * max_magnitude` fits into i64.
+    pub fn new(
+        dial: Dial,
+        method: Method,
+        instructions: usize,
+        max_magnitude: i64,
+    ) -> Result<Generator, String> {
+        let fits = i64::try_from(instructions)
+            .ok()
+            .and_then(|instructions| instructions.checked_mul(max_magnitude))
+            .is_some();
+        if !fits {
+            return Err(format!(
+                "{} instructions of up to {} clicks may produce passwords beyond {}",
+                instructions,
+                max_magnitude,
+                i64::MAX
+            ));
+        }
+        let size = dial.size() as i64;
+        let on_dial = (dial.left()..=dial.right()).contains(&dial.target());
+        let target = dial.target() as i128 - dial.left() as i128;
+        let longest = size.min(max_magnitude);
+        let mut min = vec![vec![0; size as usize]];
+        let mut max = vec![vec![0; size as usize]];
+        for steps in 1..=instructions {
+            let next_min = SparseTable::new(&min[steps - 1], u64::min);
+            let next_max = SparseTable::new(&max[steps - 1], u64::max);
+            let mut step_min = vec![u64::MAX; size as usize];
+            let mut step_max = vec![0; size as usize];
+            for from in 0..size {
+                let at = Dial::new(dial.left(), dial.right(), dial.left() + from)
+                    .with_target(dial.target());
+                for direction in [1, -1] {
+                    let mut cuts = vec![1, longest + 1];
+                    if on_dial {
+                        // first click that points at the target
+                        let mut first = (direction as i128 * (target - from as i128))
+                            .rem_euclid(size as i128)
+                            as i64;
+                        if first == 0 {
+                            first = size;
+                        }
+                        cuts.extend([first, first + 1]);
+                    }
+                    let turns = (max_magnitude - 1) / size;
+                    cuts.push(max_magnitude - turns * size + 1);
+                    cuts.retain(|&cut| (1..=longest + 1).contains(&cut));
+                    cuts.sort_unstable();
+                    cuts.dedup();
+                    for window in cuts.windows(2) {
+                        let (first, end) = (window[0], window[1]);
+                        let rotation = direction * first;
+                        let turns = (max_magnitude - first) / size;
+                        let lowest = contribution(&at, rotation, method);
+                        let highest =
+                            contribution(&at, rotation + direction * turns * size, method);
+                        let start = if direction > 0 {
+                            from + first
+                        } else {
+                            from - (end - 1)
+                        };
+                        let start = start.rem_euclid(size) as usize;
+                        let len = (end - first) as usize;
+                        step_min[from as usize] =
+                            step_min[from as usize].min(lowest + next_min.query(start, len));
+                        step_max[from as usize] =
+                            step_max[from as usize].max(highest + next_max.query(start, len));
+                    }
+                }
+            }
+            min.push(step_min);
+            max.push(step_max);
+        }
+        Ok(Generator {
+            dial,
+            method,
+            max_magnitude,
+            min,
+            max,
+        })
+    }
+
+    pub fn reachable(&self) -> (u64, u64) {
+        let instructions = self.min.len() - 1;
+        let start = (self.dial.position() - self.dial.left()) as usize;
+        (self.min[instructions][start], self.max[instructions][start])
+    }
+
+    fn try_generate(&self, password: u64, rng: &mut Rng) -> Option<Vec<i64>> {
+        let size = self.dial.size() as i64;
+        let mut dial = self.dial;
+        let mut remaining = password;
+        let mut instructions = Vec::new();
+        for steps in (0..self.min.len() - 1).rev() {
+            let from = dial.position() - dial.left();
+            let mut candidates = moves(from, size, self.max_magnitude);
+            for i in (1..candidates.len()).rev() {
+                candidates.swap(i, rng.below(i as u64 + 1) as usize);
+            }
+            let mut chosen = None;
+            for (rotation, turns) in candidates {
+                let to = (from + rotation).rem_euclid(size) as usize;
+                let (next_min, next_max) = (self.min[steps][to], self.max[steps][to]);
+                // every extra full turn passes the target exactly once more (or never)
+                let lowest = contribution(&dial, rotation, self.method);
+                let per_turn = if turns > 0 {
+                    contribution(&dial, rotation + rotation.signum() * size, self.method) - lowest
+                } else {
+                    0
+                };
+                let feasible = |turn: i64| {
+                    let c = lowest + per_turn * turn as u64;
+                    c <= remaining && (next_min..=next_max).contains(&(remaining - c))
+                };
+                let turn = if per_turn == 0 {
+                    rng.below(turns as u64 + 1) as i64
+                } else {
+                    // remaining - c has to end up in next_min..=next_max
+                    let low = remaining.saturating_sub(lowest + next_max) as i64;
+                    let high = (remaining.saturating_sub(lowest + next_min) as i64).min(turns);
+                    if low > high {
+                        continue;
+                    }
+                    low + rng.below((high - low) as u64 + 1) as i64
+                };
+                if feasible(turn) {
+                    chosen = Some(rotation + rotation.signum() * turn * size);
+                    break;
+                }
+            }
+            let rotation = chosen?;
+            let (_, passes) = dial.rotate(rotation);
+            remaining -= count(self.method, passes, dial.is_on_target());
+            instructions.push(rotation);
+        }
+        (remaining == 0).then_some(instructions)
+    }
+
+    /// Builds instructions that produce `password`, checked against the forward solver.
+    pub fn generate(&self, password: u64, seed: u64) -> Result<Vec<i64>, String> {
+        let (lowest, highest) = self.reachable();
+        if !(lowest..=highest).contains(&password) {
+            return Err(format!(
+                "Password {} is not reachable, possible are {}..={}",
+                password, lowest, highest
+            ));
+        }
+        let mut rng = Rng::new(seed);
+        for _ in 0..100 {
+            let Some(instructions) = self.try_generate(password, &mut rng) else {
+                continue;
+            };
+            let trace = run(self.dial, &instructions, self.method, Algorithm::ClosedForm);
+            let solved = trace.last().map_or(0, |step| step.password);
+            if solved != password {
+                return Err(format!(
+                    "Forward solver disagrees: generated for {} but got {}",
+                    password, solved
+                ));
+            }
+            return Ok(instructions);
+        }
+        Err(format!("Could not find instructions for {}", password))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // lowest and highest password over every sequence of smallest rotations
+    // with as many extra full turns as fit, the way the table used to be built
+    fn brute_force_reachable(
+        dial: Dial,
+        method: Method,
+        instructions: usize,
+        max_magnitude: i64,
+    ) -> (u64, u64) {
+        let size = dial.size() as i64;
+        let mut min = vec![0; size as usize];
+        let mut max = vec![0; size as usize];
+        for _ in 0..instructions {
+            let mut step_min = vec![u64::MAX; size as usize];
+            let mut step_max = vec![0; size as usize];
+            for from in 0..size {
+                let at = Dial::new(dial.left(), dial.right(), dial.left() + from)
+                    .with_target(dial.target());
+                for (rotation, turns) in moves(from, size, max_magnitude) {
+                    let to = (from + rotation).rem_euclid(size) as usize;
+                    let lowest = contribution(&at, rotation, method);
+                    let highest =
+                        contribution(&at, rotation + rotation.signum() * turns * size, method);
+                    step_min[from as usize] = step_min[from as usize].min(lowest + min[to]);
+                    step_max[from as usize] = step_max[from as usize].max(highest + max[to]);
+                }
+            }
+            (min, max) = (step_min, step_max);
+        }
+        let start = (dial.position() - dial.left()) as usize;
+        (min[start], max[start])
+    }
+
+    #[test]
+    fn table_matches_brute_force() {
+        for (left, size, target) in [(0, 7, 0), (-3, 5, 4), (2, 6, 9), (0, 1, 0), (0, 10, 3)] {
+            let right = left + size - 1;
+            for start in left..=right {
+                let dial = Dial::new(left, right, start).with_target(target);
+                for method in [Method::Landings, Method::Passes, Method::Both] {
+                    for max_magnitude in [1, 2, size - 1, size, size + 1, 3 * size + 2] {
+                        if max_magnitude < 1 {
+                            continue;
+                        }
+                        let generator = Generator::new(dial, method, 3, max_magnitude).unwrap();
+                        assert_eq!(
+                            generator.reachable(),
+                            brute_force_reachable(dial, method, 3, max_magnitude),
+                            "{:?} {:?} max magnitude {}",
+                            dial,
+                            method,
+                            max_magnitude
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn generated_instructions_solve_to_the_password() {
+        let dial = Dial::new(0, 99, 50);
+        for method in [Method::Landings, Method::Passes, Method::Both] {
+            let generator = Generator::new(dial, method, 10, 1000).unwrap();
+            let (lowest, highest) = generator.reachable();
+            for password in [lowest, (lowest + highest) / 2, highest] {
+                let instructions = generator.generate(password, 7).unwrap();
+                assert_eq!(instructions.len(), 10);
+                let trace = run(dial, &instructions, method, Algorithm::StepByStep);
+                assert_eq!(trace.last().unwrap().password, password);
+            }
+        }
+    }
+
+    #[test]
+    fn passwords_up_to_i64_max_do_not_overflow() {
+        let dial = Dial::new(0, 0, 0);
+        let max_magnitude = i64::MAX / 3;
+        let generator = Generator::new(dial, Method::Passes, 3, max_magnitude).unwrap();
+        let (lowest, highest) = generator.reachable();
+        assert_eq!(highest, 3 * (max_magnitude as u64 - 1));
+        let instructions = generator.generate(highest, 0).unwrap();
+        let trace = run(dial, &instructions, Method::Passes, Algorithm::ClosedForm);
+        assert_eq!(trace.last().unwrap().password, highest);
+        assert_eq!(generator.generate(lowest, 0).unwrap().len(), 3);
+        assert!(Generator::new(dial, Method::Passes, 3, max_magnitude + 1).is_err());
+        assert!(Generator::new(dial, Method::Passes, 3, i64::MAX).is_err());
+    }
+}
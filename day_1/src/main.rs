@@ -1,82 +1,327 @@
-// Start 50
-// min 0, max 99
+// Start 50 (--start)
+// min 0, max 99 (--min, --size)
 // R8 -> +8
 // L8 -> -8
 // 0 + L1 -> 99
 
-// Password: num of dial points visited at 0
-use std::fs;
+// Password: num of dial points visited at 0 (--target)
+use std::{fs, thread, time::Duration};
 
-#[allow(unused_imports)]
-use utils::{get_rotations, get_rotations_number, map_number};
+use clap::{Parser, ValueEnum};
+use utils::{Dial, input::Input};
 
-#[derive(PartialEq)]
+mod generator;
+
+use generator::Generator;
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
 enum Method {
-    Standard,
-    Advanced,
+    /// count the rotations that end on the target
+    Landings,
+    /// count the clicks during a rotation that pass the target
+    Passes,
+    /// count every click that points at the target
+    Both,
 }
 
-fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() < 2 {
-        println!("Usage: {} <filename>", args[0]);
-        std::process::exit(1);
-    }
-    let filename = &args[1];
-    let method: Method;
-    if args.len() < 3 {
-        method = Method::Standard;
-    } else {
-        match args[2].as_str() {
-            "standard" => method = Method::Standard,
-            "0x434C49434B" => method = Method::Advanced,
-            _ => {
-                println!("Invalid method");
-                std::process::exit(1);
-            }
+#[derive(Parser, Debug)]
+#[command(allow_negative_numbers = true)]
+struct Args {
+    /// file with one L<clicks> / R<clicks> instruction per line,
+    /// written instead of read with --generate
+    filename: String,
+    /// number of positions on the dial
+    #[arg(long, default_value_t = 100)]
+    size: i64,
+    /// lowest number on the dial
+    #[arg(long, default_value_t = 0)]
+    min: i64,
+    /// position the dial starts at
+    #[arg(long, default_value_t = 50)]
+    start: i64,
+    /// number whose visits make up the password
+    #[arg(long, default_value_t = 0)]
+    target: i64,
+    #[arg(long, value_enum, default_value_t = Method::Landings)]
+    method: Method,
+    #[arg(long, value_enum, default_value_t = Algorithm::ClosedForm)]
+    algorithm: Algorithm,
+    /// write a step by step trace to this file
+    #[arg(long)]
+    trace: Option<String>,
+    #[arg(long, value_enum, default_value_t = TraceFormat::Csv)]
+    trace_format: TraceFormat,
+    /// draw the dial in the terminal after every rotation
+    #[arg(long)]
+    animate: bool,
+    /// delay between two animation frames
+    #[arg(long, default_value_t = 200)]
+    frame_ms: u64,
+    /// write instructions that produce this password instead of solving
+    #[arg(long)]
+    generate: Option<u64>,
+    /// number of instructions to generate
+    #[arg(long, default_value_t = 10)]
+    instructions: usize,
+    /// largest number of clicks in a generated instruction
+    #[arg(long, default_value_t = 1000)]
+    max_magnitude: i64,
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum Algorithm {
+    /// O(1) per rotation
+    ClosedForm,
+    /// walk the dial click by click
+    StepByStep,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum TraceFormat {
+    Csv,
+    /// one JSON object per line
+    Jsonl,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct TraceStep {
+    rotation: i64,
+    before: i64,
+    after: i64,
+    passes: u64,
+    password: u64,
+}
+
+impl TraceStep {
+    const CSV_HEADER: &str = "instruction,before,after,passes,password";
+
+    fn instruction(&self) -> String {
+        if self.rotation < 0 {
+            format!("L{}", self.rotation.unsigned_abs())
+        } else {
+            format!("R{}", self.rotation)
         }
     }
 
-    let content: String = fs::read_to_string(filename).expect("Error reading file");
-    let content: Vec<&str> = content.lines().collect();
+    fn to_csv(self) -> String {
+        format!(
+            "{},{},{},{},{}",
+            self.instruction(),
+            self.before,
+            self.after,
+            self.passes,
+            self.password
+        )
+    }
 
-    let mut pos = 50;
-    let mut password = 0;
-    for line in content.iter() {
-        let first_character = line.chars().next();
-        let mut number = line[1..].parse::<i32>().unwrap();
-        match first_character {
-            Some('L') => number = -number,
-            Some('R') => {}
-            _ => {
-                panic!("Invalid input");
+    fn to_json(self) -> String {
+        format!(
+            "{{\"instruction\":\"{}\",\"before\":{},\"after\":{},\"passes\":{},\"password\":{}}}",
+            self.instruction(),
+            self.before,
+            self.after,
+            self.passes,
+            self.password
+        )
+    }
+}
+
+fn parse_instructions(content: &str) -> Vec<i64> {
+    content
+        .lines()
+        .map(|line| {
+            let number = line[1..].parse::<i64>().unwrap();
+            match line.chars().next() {
+                Some('L') => -number,
+                Some('R') => number,
+                _ => {
+                    panic!("Invalid input");
+                }
             }
+        })
+        .collect()
+}
+
+// password contribution of a single rotation that passed the target `passes` times
+fn count(method: Method, passes: u64, landed: bool) -> u64 {
+    match method {
+        Method::Landings => landed as u64,
+        Method::Passes => passes,
+        Method::Both => passes + landed as u64,
+    }
+}
+
+fn run(
+    mut dial: Dial,
+    instructions: &[i64],
+    method: Method,
+    algorithm: Algorithm,
+) -> Vec<TraceStep> {
+    let mut password = 0;
+    let mut trace = Vec::with_capacity(instructions.len());
+    for &rotation in instructions {
+        let before = dial.position();
+        let (after, passes) = match algorithm {
+            Algorithm::ClosedForm => dial.rotate(rotation),
+            Algorithm::StepByStep => dial.rotate_stepwise(rotation),
+        };
+        password += count(method, passes, dial.is_on_target());
+        trace.push(TraceStep {
+            rotation,
+            before,
+            after,
+            passes,
+            password,
+        });
+    }
+    trace
+}
+
+fn write_trace(trace: &[TraceStep], format: TraceFormat, filename: &str) {
+    let mut s = String::new();
+    if format == TraceFormat::Csv {
+        s.push_str(TraceStep::CSV_HEADER);
+        s.push('\n');
+    }
+    for step in trace {
+        match format {
+            TraceFormat::Csv => s.push_str(&step.to_csv()),
+            TraceFormat::Jsonl => s.push_str(&step.to_json()),
         }
-        // map pos back to 0 to 99
-        let mut advanced_method_str = "".to_string();
-        if method == Method::Advanced {
-            let rotations = get_rotations(pos, number, 0, 99);
-            if (pos + number) > 99 && (pos + number) % 100 != 0 {
-                assert!(rotations > 0, "{} {} {}", pos, number, rotations);
-            }
-            if rotations > 0 {
-                password += rotations;
-                advanced_method_str =
-                    format!(" during this rotation it points to 0 {} times", rotations).to_string();
+        s.push('\n');
+    }
+    fs::write(filename, s).expect("Error writing trace");
+}
+
+// draws the dial as a ring of ticks, '@' marks the position and 'T' the target
+fn render_dial(dial: &Dial) -> String {
+    const RADIUS: f64 = 9.0;
+    let height = 2 * RADIUS as usize + 1;
+    let width = 4 * RADIUS as usize + 1;
+    let mut canvas = vec![vec![' '; width]; height];
+    let size = dial.size();
+    let mut plot = |position: i128, c: char| {
+        let angle =
+            2.0 * std::f64::consts::PI * (position - dial.left() as i128) as f64 / size as f64;
+        let x = (2.0 * RADIUS * (1.0 + angle.sin())).round() as usize;
+        let y = (RADIUS * (1.0 - angle.cos())).round() as usize;
+        canvas[y][x] = c;
+    };
+    let ticks = size.min(96);
+    for tick in 0..ticks {
+        plot(dial.left() as i128 + tick * size / ticks, '.');
+    }
+    if (dial.left()..=dial.right()).contains(&dial.target()) {
+        plot(dial.target() as i128, 'T');
+    }
+    plot(dial.position() as i128, '@');
+
+    let label = dial.position().to_string();
+    let label_start = (width - label.len().min(width)) / 2;
+    for (i, c) in label.chars().take(width).enumerate() {
+        canvas[height / 2][label_start + i] = c;
+    }
+    canvas
+        .iter()
+        .map(|row| row.iter().collect::<String>().trim_end().to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn generate(args: &Args, password: u64, max: i64) {
+    if args.size > 10_000 || args.max_magnitude < 1 {
+        println!(
+            "Generating needs a dial of at most 10000 positions and a max magnitude of at least 1"
+        );
+        std::process::exit(1);
+    }
+    let dial = Dial::new(args.min, max, args.start).with_target(args.target);
+    match Generator::new(dial, args.method, args.instructions, args.max_magnitude)
+        .and_then(|generator| generator.generate(password, args.seed))
+    {
+        Ok(instructions) => {
+            let trace = run(dial, &instructions, args.method, Algorithm::ClosedForm);
+            let mut s = String::new();
+            for step in trace {
+                s.push_str(&step.instruction());
+                s.push('\n');
             }
+            fs::write(&args.filename, s).expect("Error writing file");
+            println!(
+                "Wrote {} instructions with password {} to {}",
+                instructions.len(),
+                password,
+                args.filename
+            );
         }
-        pos += number;
-        pos = map_number(pos, 0, 99);
-        if pos == 0 {
-            password += 1;
+        Err(e) => {
+            println!("{}", e);
+            std::process::exit(1);
         }
+    }
+}
 
+fn main() {
+    let args = Args::parse();
+    if args.size < 1 {
+        println!("Dial size has to be at least 1");
+        std::process::exit(1);
+    }
+    let max = args.min + args.size - 1;
+    if !(args.min..=max).contains(&args.start) {
         println!(
+            "Start {} is not on the dial {}..={}",
+            args.start, args.min, max
+        );
+        std::process::exit(1);
+    }
+
+    if let Some(password) = args.generate {
+        generate(&args, password, max);
+        return;
+    }
+
+    let content = Input::read(&args.filename).expect("Error reading file");
+    let instructions = parse_instructions(content.as_str());
+
+    let mut dial = Dial::new(args.min, max, args.start).with_target(args.target);
+    let trace = run(dial, &instructions, args.method, args.algorithm);
+    if let Some(filename) = &args.trace {
+        write_trace(&trace, args.trace_format, filename);
+    }
+
+    for step in &trace {
+        let mut passes_str = "".to_string();
+        if args.method != Method::Landings && step.passes > 0 {
+            passes_str = format!(
+                " during this rotation it points to {} {} times",
+                args.target, step.passes
+            );
+        }
+        let sentence = format!(
             "The dial is rotated {} to point at {} {}",
-            line, pos, advanced_method_str
+            step.instruction(),
+            step.after,
+            passes_str
         );
+        if args.animate {
+            dial.rotate(step.rotation);
+            // clear the terminal and move the cursor home
+            print!("\x1b[2J\x1b[H");
+            println!(
+                "{}\n\n{}\nPassword: {}",
+                render_dial(&dial),
+                sentence,
+                step.password
+            );
+            thread::sleep(Duration::from_millis(args.frame_ms));
+        } else {
+            println!("{}", sentence);
+        }
     }
-    println!("Password: {}", password);
+    println!("Password: {}", trace.last().map_or(0, |step| step.password));
 }
 
 // 6106
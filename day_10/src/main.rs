@@ -4,7 +4,7 @@ use std::{
 };
 
 use mt_logger::*;
-use utils::create_vector_from_tuple_string;
+use utils::{create_vector_from_tuple_string, input::Input};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct Button_Logic {
@@ -321,8 +321,8 @@ fn main() {
         std::process::exit(1);
     }
     let filepath = &args[1];
-    let filecontent = std::fs::read_to_string(filepath).expect("Could not read file");
-    let machines = Machines::from_string(&filecontent);
+    let filecontent = Input::read(filepath).expect("Could not read file");
+    let machines = Machines::from_string(filecontent.as_str());
 
     //mt_log!(Level::Debug, "{:?}", machines);
     //machines.part_1();
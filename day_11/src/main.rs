@@ -1,6 +1,7 @@
 use std::collections::{HashMap, HashSet, VecDeque};
 
 use mt_logger::*;
+use utils::input::Input;
 
 struct Node {
     id: usize,
@@ -193,8 +194,8 @@ fn main() {
         std::process::exit(1);
     }
     let filepath = &args[1];
-    let filecontent = std::fs::read_to_string(filepath).expect("Could not read file");
-    let graph = parse_graph(&filecontent);
+    let filecontent = Input::read(filepath).expect("Could not read file");
+    let graph = parse_graph(filecontent.as_str());
     mt_log!(Level::Debug, "Graph: {:?}", graph);
     let results = find_all_paths(&graph, "you", "out");
     mt_log!(Level::Info, "Result Part 1: {}", results.len());
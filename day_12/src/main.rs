@@ -1,5 +1,6 @@
 use mt_logger::*;
 use std::collections::HashMap;
+use utils::input::Input;
 
 #[derive(Debug)]
 struct Shape {
@@ -112,9 +113,9 @@ fn main() {
     }
 
     let filepath = &args[1];
-    let filecontent = std::fs::read_to_string(filepath).expect("Could not read file");
+    let filecontent = Input::read(filepath).expect("Could not read file");
 
-    let puzzle = parse_puzzle(&filecontent);
+    let puzzle = parse_puzzle(filecontent.as_str());
 
     mt_log!(Level::Info, "Parsed puzzle:\n{:#?}", puzzle);
     puzzle.part_1();
@@ -1,3 +1,6 @@
+use clap::{Parser, ValueEnum};
+use utils::input::Input;
+
 #[derive(Debug)]
 struct Interval {
     start: i64,
@@ -22,43 +25,424 @@ impl Interval {
     }
 }
 
-fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() < 2 {
-        println!("Usage: {} <filename>", args[0]);
-        std::process::exit(1);
-    }
-    let filename = &args[1];
-    let content: String = std::fs::read_to_string(filename).expect("Error reading file");
-    let intervals = Interval::from_string(&content);
-    let mut count: i64 = 0;
-    for interval in intervals.iter() {
-        count += find_invalid_ids(interval);
-    }
-    println!("{}", count);
-}
-
-fn find_invalid_ids(interval: &Interval) -> i64 {
-    let mut count: i64 = 0;
-    for i in interval.start..interval.end + 1 {
-        // put 2 for part 1 and usize::MAX-1 for part 2
-        if is_invalid_id(i, usize::MAX - 5) {
-            count += i;
+// which ids count as invalid: a block of at least `min_block_len` digits (in `base`)
+// repeated min_repetitions..=max_repetitions times
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct RepetitionRule {
+    min_repetitions: u32,
+    max_repetitions: u32,
+    min_block_len: u32,
+    base: u32,
+}
+
+impl RepetitionRule {
+    fn part_1() -> RepetitionRule {
+        RepetitionRule::exactly(2)
+    }
+
+    fn part_2() -> RepetitionRule {
+        RepetitionRule {
+            max_repetitions: u32::MAX,
+            ..RepetitionRule::part_1()
         }
     }
-    count
+
+    fn exactly(repetitions: u32) -> RepetitionRule {
+        RepetitionRule {
+            min_repetitions: repetitions,
+            max_repetitions: repetitions,
+            min_block_len: 1,
+            base: 10,
+        }
+    }
+
+    fn format_digits(&self, mut n: i64) -> String {
+        let mut digits = Vec::new();
+        loop {
+            digits.push(
+                std::char::from_digit((n % self.base as i64) as u32, self.base).unwrap_or('?'),
+            );
+            n /= self.base as i64;
+            if n == 0 {
+                break;
+            }
+        }
+        digits.iter().rev().collect()
+    }
+
+    fn num_digits(&self, mut n: i64) -> u32 {
+        let mut digits = 1;
+        while n >= self.base as i64 {
+            n /= self.base as i64;
+            digits += 1;
+        }
+        digits
+    }
+}
+
+#[derive(Parser, Debug)]
+#[command(about = "Sums the invalid ids of both parts, or of a custom rule if one is given")]
+struct Args {
+    /// comma separated list of a-b id ranges
+    filename: String,
+    /// exact number of block repetitions
+    #[arg(long, conflicts_with_all = ["min_repetitions", "max_repetitions"])]
+    repetitions: Option<u32>,
+    #[arg(long)]
+    min_repetitions: Option<u32>,
+    #[arg(long)]
+    max_repetitions: Option<u32>,
+    #[arg(long)]
+    min_block_len: Option<u32>,
+    /// numeric base the ids are written in when looking for repeated blocks
+    #[arg(long)]
+    base: Option<u32>,
+    /// print a per range breakdown instead of the totals
+    #[arg(long, value_enum)]
+    report: Option<ReportFormat>,
+    /// include every invalid id and its repeated block in the report
+    #[arg(long, requires = "report")]
+    list_ids: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum ReportFormat {
+    Table,
+    Json,
 }
 
-fn is_invalid_id(id: i64, repeating_maximum: usize) -> bool {
-    let id_str = id.to_string();
-    for i in 1..(id_str.len() / 2 + 1) {
-        let chars = &id_str[0..i];
-        let splits = id_str.split(chars).collect::<Vec<&str>>();
-        if splits.iter().map(|s| s.len()).sum::<usize>() == 0
-            && splits.len() < (repeating_maximum + 1)
+impl Args {
+    fn custom_rule(&self) -> Option<RepetitionRule> {
+        if self.repetitions.is_none()
+            && self.min_repetitions.is_none()
+            && self.max_repetitions.is_none()
+            && self.min_block_len.is_none()
+            && self.base.is_none()
         {
-            return true;
+            return None;
         }
+        let mut rule = RepetitionRule::part_2();
+        if let Some(repetitions) = self.repetitions {
+            rule = RepetitionRule::exactly(repetitions);
+        }
+        rule.min_repetitions = self.min_repetitions.unwrap_or(rule.min_repetitions);
+        rule.max_repetitions = self.max_repetitions.unwrap_or(rule.max_repetitions);
+        rule.min_block_len = self.min_block_len.unwrap_or(rule.min_block_len);
+        rule.base = self.base.unwrap_or(rule.base);
+        Some(rule)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct InvalidId {
+    id: i64,
+    block: i64,
+    repetitions: u32,
+}
+
+// ids are only kept when they get listed, a range may hold billions of them
+struct RangeReport<'a> {
+    interval: &'a Interval,
+    count: usize,
+    sum: i128,
+    ids: Vec<InvalidId>,
+}
+
+impl RangeReport<'_> {
+    fn new<'a>(interval: &'a Interval, rule: &RepetitionRule, list_ids: bool) -> RangeReport<'a> {
+        let mut report = RangeReport {
+            interval,
+            count: 0,
+            sum: 0,
+            ids: Vec::new(),
+        };
+        for invalid in repeated_ids(interval, rule) {
+            report.count += 1;
+            report.sum += invalid.id as i128;
+            if list_ids {
+                report.ids.push(invalid);
+            }
+        }
+        report.ids.sort_unstable_by_key(|invalid| invalid.id);
+        report
+    }
+}
+
+fn total(reports: &[RangeReport]) -> i128 {
+    reports.iter().map(|r| r.sum).sum()
+}
+
+fn print_table(name: &str, rule: &RepetitionRule, reports: &[RangeReport], list_ids: bool) {
+    println!("{}", name);
+    println!("{:>25} {:>8} {:>20}", "range", "count", "sum");
+    for report in reports {
+        println!(
+            "{:>25} {:>8} {:>20}",
+            format!("{}-{}", report.interval.start, report.interval.end),
+            report.count,
+            report.sum
+        );
+        if list_ids {
+            for invalid in &report.ids {
+                println!(
+                    "{:>25} = {} x{}",
+                    rule.format_digits(invalid.id),
+                    rule.format_digits(invalid.block),
+                    invalid.repetitions
+                );
+            }
+        }
+    }
+    println!(
+        "{:>25} {:>8} {:>20}",
+        "total",
+        reports.iter().map(|r| r.count).sum::<usize>(),
+        total(reports)
+    );
+}
+
+fn to_json(name: &str, rule: &RepetitionRule, reports: &[RangeReport], list_ids: bool) -> String {
+    let ranges = reports
+        .iter()
+        .map(|report| {
+            let mut s = format!(
+                "{{\"start\":{},\"end\":{},\"count\":{},\"sum\":{}",
+                report.interval.start, report.interval.end, report.count, report.sum
+            );
+            if list_ids {
+                let ids = report
+                    .ids
+                    .iter()
+                    .map(|invalid| {
+                        format!(
+                            "{{\"id\":{},\"digits\":\"{}\",\"block\":\"{}\",\"repetitions\":{}}}",
+                            invalid.id,
+                            rule.format_digits(invalid.id),
+                            rule.format_digits(invalid.block),
+                            invalid.repetitions
+                        )
+                    })
+                    .collect::<Vec<String>>();
+                s.push_str(&format!(",\"ids\":[{}]", ids.join(",")));
+            }
+            s.push('}');
+            s
+        })
+        .collect::<Vec<String>>();
+    format!(
+        "{{\"rule\":\"{}\",\"total\":{},\"ranges\":[{}]}}",
+        name,
+        total(reports),
+        ranges.join(",")
+    )
+}
+
+fn main() {
+    let args = Args::parse();
+    let content = Input::read(&args.filename).expect("Error reading file");
+    let intervals = Interval::from_string(content.as_str());
+
+    let rules = match args.custom_rule() {
+        Some(rule) => vec![("Custom rule", rule)],
+        None => vec![
+            ("Part 1", RepetitionRule::part_1()),
+            ("Part 2", RepetitionRule::part_2()),
+        ],
+    };
+    let mut json = Vec::new();
+    for (name, rule) in rules {
+        if rule.base < 2 || rule.base > 36 || rule.min_repetitions < 2 || rule.min_block_len < 1 {
+            println!("A rule needs a base in 2..=36, >= 2 repetitions and blocks of >= 1 digit");
+            std::process::exit(1);
+        }
+        let reports = intervals
+            .iter()
+            .map(|interval| RangeReport::new(interval, &rule, args.list_ids))
+            .collect::<Vec<RangeReport>>();
+        match args.report {
+            None => println!("Result {}: {}", name, total(&reports)),
+            Some(ReportFormat::Table) => print_table(name, &rule, &reports, args.list_ids),
+            Some(ReportFormat::Json) => json.push(to_json(name, &rule, &reports, args.list_ids)),
+        }
+    }
+    if args.report == Some(ReportFormat::Json) {
+        println!("[{}]", json.join(","));
+    }
+}
+
+// all ids in the interval that match the rule, generated as block * 11..1 with the
+// ones spaced block_len digits apart, by length but not sorted within a length
+fn repeated_ids(interval: &Interval, rule: &RepetitionRule) -> impl Iterator<Item = InvalidId> {
+    let rule = *rule;
+    let base = rule.base as i128;
+    let start = interval.start.max(1);
+    let end = interval.end;
+    // no lengths at all for ranges without a positive id
+    let longest = if end < 1 { 0 } else { rule.num_digits(end) };
+    (rule.num_digits(start)..=longest).flat_map(move |length| {
+        let block_lens: Vec<u32> = (rule.min_block_len..=length / rule.min_repetitions)
+            .filter(|&block_len| {
+                length % block_len == 0
+                    && (rule.min_repetitions..=rule.max_repetitions).contains(&(length / block_len))
+            })
+            .collect();
+        let multipliers: Vec<i128> = block_lens
+            .iter()
+            .map(|&block_len| (base.pow(length) - 1) / (base.pow(block_len) - 1))
+            .collect();
+        (0..block_lens.len()).flat_map(move |i| {
+            let (block_len, multiplier) = (block_lens[i], multipliers[i]);
+            // e.g. 1111 is 1 x4 and 11 x2, keep the shortest block
+            let shorter = multipliers[..i].to_vec();
+            let lowest_block = base
+                .pow(block_len - 1)
+                .max((start as i128 + multiplier - 1) / multiplier);
+            let highest_block = (base.pow(block_len) - 1).min(end as i128 / multiplier);
+            (lowest_block..=highest_block).filter_map(move |block| {
+                let id = block * multiplier;
+                if shorter.iter().any(|m| id % m == 0) {
+                    return None;
+                }
+                Some(InvalidId {
+                    id: id as i64,
+                    block: block as i64,
+                    repetitions: length / block_len,
+                })
+            })
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // the checker the solver used before ids were generated
+    fn is_invalid_id(id: i64, repeating_maximum: usize) -> bool {
+        let id_str = id.to_string();
+        for i in 1..(id_str.len() / 2 + 1) {
+            let chars = &id_str[0..i];
+            let splits = id_str.split(chars).collect::<Vec<&str>>();
+            if splits.iter().map(|s| s.len()).sum::<usize>() == 0
+                && splits.len() < (repeating_maximum + 1)
+            {
+                return true;
+            }
+        }
+        false
+    }
+
+    // the rule applied to the digits of a single id
+    fn matches_rule(id: i64, rule: &RepetitionRule) -> bool {
+        let digits = rule.format_digits(id);
+        let length = digits.len() as u32;
+        (rule.min_block_len..=length / 2).any(|block_len| {
+            let repetitions = length / block_len;
+            length.is_multiple_of(block_len)
+                && (rule.min_repetitions..=rule.max_repetitions).contains(&repetitions)
+                && digits[..block_len as usize].repeat(repetitions as usize) == digits
+        })
+    }
+
+    fn check(interval: &Interval, rule: &RepetitionRule, is_invalid: impl Fn(i64) -> bool) {
+        let mut generated: Vec<InvalidId> = repeated_ids(interval, rule).collect();
+        generated.sort_unstable_by_key(|invalid| invalid.id);
+        let expected: Vec<i64> = (interval.start.max(1)..=interval.end)
+            .filter(|&id| is_invalid(id))
+            .collect();
+        assert_eq!(
+            generated
+                .iter()
+                .map(|invalid| invalid.id)
+                .collect::<Vec<i64>>(),
+            expected,
+            "{:?} {:?}",
+            interval,
+            rule
+        );
+        for invalid in &generated {
+            let block = rule.format_digits(invalid.block);
+            assert_eq!(
+                block.repeat(invalid.repetitions as usize),
+                rule.format_digits(invalid.id)
+            );
+        }
+    }
+
+    fn intervals() -> impl Strategy<Value = Interval> {
+        prop_oneof![0i64..2_000, 0i64..200_000, 0i64..20_000_000]
+            .prop_flat_map(|start| (Just(start), 0i64..3_000))
+            .prop_map(|(start, len)| Interval {
+                start,
+                end: start + len,
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn part_1_matches_previous_checker(interval in intervals()) {
+            check(&interval, &RepetitionRule::part_1(), |id| is_invalid_id(id, 3));
+        }
+
+        #[test]
+        fn part_2_matches_previous_checker(interval in intervals()) {
+            check(&interval, &RepetitionRule::part_2(), |id| is_invalid_id(id, usize::MAX - 5));
+        }
+
+        #[test]
+        fn custom_rule_matches_digit_check(
+            interval in intervals(),
+            base in 2u32..=16,
+            min_repetitions in 2u32..=4,
+            extra_repetitions in 0u32..=3,
+            min_block_len in 1u32..=3,
+        ) {
+            let rule = RepetitionRule {
+                min_repetitions,
+                max_repetitions: min_repetitions + extra_repetitions,
+                min_block_len,
+                base,
+            };
+            check(&interval, &rule, |id| matches_rule(id, &rule));
+        }
+    }
+
+    #[test]
+    fn solves_example() {
+        let intervals = Interval::from_string(
+            "11-22,95-115,998-1012,1188511880-1188511890,222220-222224,\
+1698522-1698528,446443-446449,38593856-38593862,565653-565659,\
+824824821-824824827,2121212118-2121212124",
+        );
+        let sum = |rule: &RepetitionRule| -> i64 {
+            intervals
+                .iter()
+                .flat_map(|interval| repeated_ids(interval, rule))
+                .map(|invalid| invalid.id)
+                .sum()
+        };
+        assert_eq!(sum(&RepetitionRule::part_1()), 1227775554);
+        assert_eq!(sum(&RepetitionRule::part_2()), 4174379265);
+        assert_eq!(
+            repeated_ids(&Interval { start: 1, end: 7 }, &RepetitionRule::exactly(2)).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn sums_past_i64_max() {
+        let interval = Interval {
+            start: 1000000000000000000,
+            end: i64::MAX,
+        };
+        // 19 digits only split into 19 blocks of one, 1111111111111111111 x1..=8
+        let report = RangeReport::new(&interval, &RepetitionRule::part_2(), false);
+        assert_eq!(report.count, 8);
+        assert_eq!(report.sum, 1111111111111111111 * 36);
+        assert!(report.ids.is_empty());
+        let report = RangeReport::new(&interval, &RepetitionRule::part_1(), true);
+        assert_eq!((report.count, report.sum), (0, 0));
+        assert_eq!(total(&[report]), 0);
     }
-    false
 }
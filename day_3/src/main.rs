@@ -1,70 +1,161 @@
+use std::fmt;
+
+use clap::Parser;
 use mt_logger::*;
+use num_bigint::BigUint;
+use utils::input::Input;
+
+#[derive(Parser, Debug)]
+struct Args {
+    /// file with one bank of digits per line
+    filename: String,
+    /// number of digits to turn on per bank, repeat to solve several at once
+    #[arg(long = "digits", default_values_t = [2, 12])]
+    digits: Vec<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+enum BankError {
+    NotADigit { column: usize, c: char },
+    TooShort { length: usize, n: usize },
+}
+
+impl fmt::Display for BankError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BankError::NotADigit { column, c } => {
+                write!(f, "'{}' at column {} is not a digit", c, column + 1)
+            }
+            BankError::TooShort { length, n } => {
+                write!(f, "{} digits are too few to pick {}", length, n)
+            }
+        }
+    }
+}
 
 fn main() {
     mt_new!(None, Level::Info, OutputStream::StdOut, true);
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() < 2 {
-        println!("Usage: {} <filename>", args[0]);
-        std::process::exit(1);
-    }
-    let filename = &args[1];
-    let content: String = std::fs::read_to_string(filename).expect("Error reading file");
+    let args = Args::parse();
+    let content = Input::read(&args.filename).expect("Error reading file");
     let content: Vec<&str> = content.lines().collect();
 
-    let mut total_joltage = 0;
-    for string_number in content {
-        total_joltage += find_largest_n_number(string_number, 12);
+    for &n in &args.digits {
+        let mut total_joltage = BigUint::ZERO;
+        for (line, string_number) in content.iter().enumerate() {
+            match largest_subsequence(string_number, n) {
+                Ok((joltage, _)) => total_joltage += joltage,
+                Err(e) => {
+                    mt_log!(Level::Error, "Line {}: {}", line + 1, e);
+                    mt_flush!().unwrap();
+                    std::process::exit(1);
+                }
+            }
+        }
+        mt_log!(Level::Info, "Result for {} digits: {}", n, total_joltage);
     }
-    mt_log!(Level::Info, "{}", total_joltage);
     mt_flush!().unwrap();
 }
-fn find_largest_n_number(string_number: &str, n: usize) -> u128 {
-    assert!(string_number.len() >= n);
-    // find biggest number and second biggest number which is right of biggest number
-    let mut numbers: Vec<Option<u128>> = vec![None; n];
-    // initiate Vec
 
-    let mut current_number: u128;
-    let length = string_number.len();
-    mt_log!(
-        Level::Debug,
-        "trying to find largest number in: {}",
-        string_number
-    );
-    for (i, c) in string_number.chars().enumerate() {
-        current_number = c.to_digit(10).unwrap().into();
-        mt_log!(Level::Debug, "current_number is {}", current_number);
-        let mut new_numbers = numbers.clone();
-        let mut replaced_flag = false;
-        for (j, number) in numbers.iter().enumerate() {
-            mt_log!(
-                Level::Debug,
-                "RF: {}, numbers: {:?}, new_numbers: {:?}, main_condi: {}",
-                replaced_flag,
-                numbers,
-                new_numbers,
-                (length - i) >= (n - j)
-            );
-            if replaced_flag {
-                new_numbers[j] = None;
-            } else if (length - i) >= (n - j)
-                && (number.is_none() || number.unwrap() < current_number)
-            {
-                mt_log!(Level::Debug, "found new highest number for position {}", j);
-                new_numbers[j] = Some(current_number);
-                replaced_flag = true;
+// greedy monotonic stack: a digit replaces smaller digits before it as long as enough
+// digits are left to still pick n, returns the number and the positions of its digits
+fn largest_subsequence(string_number: &str, n: usize) -> Result<(BigUint, Vec<usize>), BankError> {
+    let digits = string_number
+        .chars()
+        .enumerate()
+        .map(|(column, c)| c.to_digit(10).ok_or(BankError::NotADigit { column, c }))
+        .collect::<Result<Vec<u32>, BankError>>()?;
+    let length = digits.len();
+    if length < n {
+        return Err(BankError::TooShort { length, n });
+    }
+    let mut stack: Vec<(u32, usize)> = Vec::with_capacity(n);
+    for (i, &digit) in digits.iter().enumerate() {
+        while let Some(&(top, _)) = stack.last() {
+            if top >= digit || stack.len() - 1 + (length - i) < n {
+                break;
             }
+            stack.pop();
+        }
+        if stack.len() < n {
+            stack.push((digit, i));
         }
-        numbers = new_numbers;
     }
-    mt_log!(Level::Debug, "Numbers: {:?}", numbers);
+    mt_log!(Level::Debug, "Stack: {:?}", stack);
+    let chosen: Vec<u8> = stack.iter().map(|(digit, _)| *digit as u8).collect();
+    let sum = BigUint::from_radix_be(&chosen, 10).unwrap_or_default();
+    Ok((sum, stack.iter().map(|(_, i)| *i).collect()))
+}
 
-    let sum = numbers
-        .iter()
-        .rev()
-        .enumerate()
-        .map(|(i, n)| n.unwrap() * 10u128.pow(i as u32))
-        .sum::<u128>();
-    mt_log!(Level::Debug, "SUM: {}", sum);
-    sum
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn find_largest_n_number(string_number: &str, n: usize) -> u128 {
+        assert!(string_number.len() >= n);
+        // find biggest number and second biggest number which is right of biggest number
+        let mut numbers: Vec<Option<u128>> = vec![None; n];
+        // initiate Vec
+
+        let mut current_number: u128;
+        let length = string_number.len();
+        mt_log!(
+            Level::Debug,
+            "trying to find largest number in: {}",
+            string_number
+        );
+        for (i, c) in string_number.chars().enumerate() {
+            current_number = c.to_digit(10).unwrap().into();
+            mt_log!(Level::Debug, "current_number is {}", current_number);
+            let mut new_numbers = numbers.clone();
+            let mut replaced_flag = false;
+            for (j, number) in numbers.iter().enumerate() {
+                mt_log!(
+                    Level::Debug,
+                    "RF: {}, numbers: {:?}, new_numbers: {:?}, main_condi: {}",
+                    replaced_flag,
+                    numbers,
+                    new_numbers,
+                    (length - i) >= (n - j)
+                );
+                if replaced_flag {
+                    new_numbers[j] = None;
+                } else if (length - i) >= (n - j)
+                    && (number.is_none() || number.unwrap() < current_number)
+                {
+                    mt_log!(Level::Debug, "found new highest number for position {}", j);
+                    new_numbers[j] = Some(current_number);
+                    replaced_flag = true;
+                }
+            }
+            numbers = new_numbers;
+        }
+        mt_log!(Level::Debug, "Numbers: {:?}", numbers);
+
+        let sum = numbers
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, n)| n.unwrap() * 10u128.pow(i as u32))
+            .sum::<u128>();
+        mt_log!(Level::Debug, "SUM: {}", sum);
+        sum
+    }
+
+    proptest! {
+        #[test]
+        fn largest_subsequence_matches_previous_implementation(
+            (string_number, n) in "[0-9]{1,40}".prop_flat_map(|s| {
+                let len = s.len();
+                (Just(s), 1..=len.min(38))
+            })
+        ) {
+            let (sum, positions) = largest_subsequence(&string_number, n).unwrap();
+            prop_assert_eq!(sum.clone(), BigUint::from(find_largest_n_number(&string_number, n)));
+            prop_assert_eq!(positions.len(), n);
+            prop_assert!(positions.windows(2).all(|w| w[0] < w[1]));
+            let picked: String = positions.iter().map(|&i| &string_number[i..i + 1]).collect();
+            prop_assert_eq!(picked.parse::<BigUint>().unwrap(), sum);
+        }
+    }
 }
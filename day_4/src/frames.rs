@@ -0,0 +1,115 @@
+use std::{fs, io, path::Path};
+
+use clap::ValueEnum;
+
+use crate::{Field, Simulation};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ImageFormat {
+    Ppm,
+    Png,
+    /// only write the text frames
+    None,
+}
+
+const EMPTY: [u8; 3] = [24, 24, 24];
+const ROLL: [u8; 3] = [200, 200, 200];
+
+// early rounds are yellow, late rounds dark red
+fn round_colour(round: usize, last_round: usize) -> [u8; 3] {
+    let t = (round - 1) as f64 / (last_round.max(2) - 1) as f64;
+    [255 - (95.0 * t) as u8, (220.0 * (1.0 - t)) as u8, 40]
+}
+
+// state after `round` rounds, removed rolls are coloured by the round they were removed in
+fn frame(
+    field: &[Vec<char>],
+    simulation: &Simulation,
+    object: char,
+    round: usize,
+) -> (Vec<Vec<char>>, Vec<Vec<[u8; 3]>>) {
+    let last_round = simulation.rounds.len();
+    let mut text = field.to_vec();
+    let mut colours = vec![vec![EMPTY; field[0].len()]; field.len()];
+    for (i, line) in field.iter().enumerate() {
+        for j in 0..line.len() {
+            let removed_in = simulation.removed_in[i][j];
+            if removed_in != 0 && removed_in <= round {
+                text[i][j] = 'x';
+                colours[i][j] = round_colour(removed_in, last_round);
+            } else if line[j] == object {
+                colours[i][j] = ROLL;
+            }
+        }
+    }
+    (text, colours)
+}
+
+fn scale_pixels(colours: &[Vec<[u8; 3]>], scale: usize) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(colours.len() * colours[0].len() * scale * scale * 3);
+    for row in colours {
+        for _ in 0..scale {
+            for colour in row {
+                for _ in 0..scale {
+                    pixels.extend_from_slice(colour);
+                }
+            }
+        }
+    }
+    pixels
+}
+
+fn write_ppm(path: &Path, width: usize, height: usize, pixels: &[u8]) -> io::Result<()> {
+    let mut data = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+    data.extend_from_slice(pixels);
+    fs::write(path, data)
+}
+
+fn write_png(path: &Path, width: usize, height: usize, pixels: &[u8]) -> io::Result<()> {
+    let file = io::BufWriter::new(fs::File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(pixels).map_err(io::Error::other)
+}
+
+// writes round_000.txt (the input) up to round_NNN.txt plus an image per round
+pub fn write_frames(
+    directory: &str,
+    field: &[Vec<char>],
+    simulation: &Simulation,
+    object: char,
+    image_format: ImageFormat,
+    scale: usize,
+) -> io::Result<()> {
+    let directory = Path::new(directory);
+    fs::create_dir_all(directory)?;
+    let scale = scale.max(1);
+    let (width, height) = (field[0].len() * scale, field.len() * scale);
+    for round in 0..=simulation.rounds.len() {
+        let (text, colours) = frame(field, simulation, object, round);
+        let name = format!("round_{:03}", round);
+        fs::write(
+            directory.join(format!("{}.txt", name)),
+            Field::to_string(Field(text)),
+        )?;
+        let pixels = scale_pixels(&colours, scale);
+        match image_format {
+            ImageFormat::Ppm => write_ppm(
+                &directory.join(format!("{}.ppm", name)),
+                width,
+                height,
+                &pixels,
+            )?,
+            ImageFormat::Png => write_png(
+                &directory.join(format!("{}.png", name)),
+                width,
+                height,
+                &pixels,
+            )?,
+            ImageFormat::None => {}
+        }
+    }
+    Ok(())
+}
@@ -1,18 +1,17 @@
+use clap::{Parser, ValueEnum};
 use mt_logger::*;
+use std::collections::VecDeque;
 use std::convert::TryFrom;
+use utils::input::Input;
+
+mod frames;
+
+use frames::ImageFormat;
 
 #[derive(Debug)]
 struct Field(Vec<Vec<char>>);
 
 impl Field {
-    fn from_string(s: &str) -> Field {
-        Field(
-            s.to_string()
-                .lines()
-                .map(|line| line.chars().collect())
-                .collect(),
-        )
-    }
     fn to_string(field: Field) -> String {
         let mut s: String = String::new();
         for row in field.0 {
@@ -25,53 +24,251 @@ impl Field {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum Neighbourhood {
+    /// all cells within the radius in both directions
+    Moore,
+    /// cells whose row and column distance add up to at most the radius
+    VonNeumann,
+    /// offsets given with --offsets or --mask
+    Custom,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum Comparison {
+    Lt,
+    Le,
+    Eq,
+    Ne,
+    Ge,
+    Gt,
+}
+
+impl Comparison {
+    fn holds(&self, count: i32, threshold: i32) -> bool {
+        match self {
+            Comparison::Lt => count < threshold,
+            Comparison::Le => count <= threshold,
+            Comparison::Eq => count == threshold,
+            Comparison::Ne => count != threshold,
+            Comparison::Ge => count >= threshold,
+            Comparison::Gt => count > threshold,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum Removal {
+    /// every round removes all rolls that match the rule at the start of the round
+    Simultaneous,
+    /// rolls are removed one by one and every removal is seen by the next check
+    Sequential,
+}
+
+// a roll gets removed when `count(neighbours) comparison threshold` holds
+#[derive(Clone, Debug)]
+struct Rule {
+    offsets: Vec<(isize, isize)>,
+    threshold: i32,
+    comparison: Comparison,
+    object: char,
+    removal: Removal,
+}
+
+impl Rule {
+    fn matches(&self, count: i32) -> bool {
+        self.comparison.holds(count, self.threshold)
+    }
+}
+
+#[derive(Parser, Debug)]
+#[command(allow_negative_numbers = true)]
+struct Args {
+    filename: String,
+    #[arg(long, value_enum, default_value_t = Neighbourhood::Moore)]
+    neighbourhood: Neighbourhood,
+    #[arg(long, default_value_t = 1)]
+    radius: usize,
+    /// custom neighbourhood as row,col offsets, e.g. "-1,0;1,0;0,-2"
+    #[arg(long, allow_hyphen_values = true)]
+    offsets: Option<String>,
+    /// file with a custom neighbourhood drawn as '#' around the centre of an odd sized square
+    #[arg(long, conflicts_with = "offsets")]
+    mask: Option<String>,
+    #[arg(long, default_value_t = 4)]
+    threshold: i32,
+    #[arg(long, value_enum, default_value_t = Comparison::Lt)]
+    comparison: Comparison,
+    #[arg(long, default_value_t = '@')]
+    object: char,
+    #[arg(long, value_enum, default_value_t = Removal::Simultaneous)]
+    removal: Removal,
+    /// file the final field gets written to
+    #[arg(long, default_value = "output.txt")]
+    output: String,
+    /// directory to keep one text frame and one image per removal round in
+    #[arg(long)]
+    frames: Option<String>,
+    #[arg(long, value_enum, default_value_t = ImageFormat::Ppm)]
+    image_format: ImageFormat,
+    /// pixels per cell in the frame images
+    #[arg(long, default_value_t = 4)]
+    scale: usize,
+    /// do not write any files
+    #[arg(long, conflicts_with_all = ["frames", "output"])]
+    no_output: bool,
+}
+
+fn parse_offsets(s: &str) -> Result<Vec<(isize, isize)>, String> {
+    s.split(';')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| {
+            let (row, col) = pair
+                .split_once(',')
+                .ok_or(format!("Offset {} is not row,col", pair))?;
+            let row = row
+                .trim()
+                .parse::<isize>()
+                .map_err(|e| format!("{}: {}", row, e))?;
+            let col = col
+                .trim()
+                .parse::<isize>()
+                .map_err(|e| format!("{}: {}", col, e))?;
+            Ok((row, col))
+        })
+        .collect()
+}
+
+fn parse_mask(mask: &Input) -> Result<Vec<(isize, isize)>, String> {
+    let rows = mask.grid()?;
+    if rows.len().is_multiple_of(2) || rows[0].len() != rows.len() {
+        return Err("Mask has to be an odd sized square".to_string());
+    }
+    let centre = (rows.len() / 2) as isize;
+    let mut offsets = Vec::new();
+    for (i, row) in rows.iter().enumerate() {
+        for (j, &c) in row.iter().enumerate() {
+            if c == '#' {
+                offsets.push((i as isize - centre, j as isize - centre));
+            }
+        }
+    }
+    Ok(offsets)
+}
+
+impl Args {
+    fn rule(&self) -> Result<Rule, String> {
+        let r = self.radius as isize;
+        let mut offsets: Vec<(isize, isize)> = match self.neighbourhood {
+            Neighbourhood::Moore => (-r..=r)
+                .flat_map(|i| (-r..=r).map(move |j| (i, j)))
+                .collect(),
+            Neighbourhood::VonNeumann => (-r..=r)
+                .flat_map(|i| (-r..=r).map(move |j| (i, j)))
+                .filter(|(i, j)| i.abs() + j.abs() <= r)
+                .collect(),
+            Neighbourhood::Custom => match (&self.offsets, &self.mask) {
+                (Some(offsets), _) => parse_offsets(offsets)?,
+                (None, Some(mask)) => {
+                    parse_mask(&Input::read(mask).map_err(|e| format!("{}: {}", mask, e))?)
+                        .map_err(|e| format!("{}: {}", mask, e))?
+                }
+                (None, None) => {
+                    return Err("Custom neighbourhood needs --offsets or --mask".to_string());
+                }
+            },
+        };
+        offsets.retain(|&offset| offset != (0, 0));
+        offsets.sort();
+        offsets.dedup();
+        Ok(Rule {
+            offsets,
+            threshold: self.threshold,
+            comparison: self.comparison,
+            object: self.object,
+            removal: self.removal,
+        })
+    }
+}
+
 fn main() {
     mt_new!(None, Level::Info, OutputStream::StdOut, true);
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() < 2 {
-        mt_log!(Level::Error, "Usage: {} <filename>", args[0]);
-        mt_flush!().unwrap();
-        std::process::exit(1);
-    }
-    let filename = &args[1];
-    let filecontent = std::fs::read_to_string(filename).expect("Error reading file");
+    let args = Args::parse();
+    let rule = match args.rule() {
+        Ok(rule) => rule,
+        Err(e) => {
+            mt_log!(Level::Error, "{}", e);
+            mt_flush!().unwrap();
+            std::process::exit(1);
+        }
+    };
+    mt_log!(Level::Debug, "{:?}", rule);
+    let filecontent = Input::read(&args.filename).expect("Error reading file");
 
-    let field: Vec<Vec<char>> = Field::from_string(&filecontent).0;
+    let field: Vec<Vec<char>> = match filecontent.grid() {
+        Ok(field) => field,
+        Err(e) => {
+            mt_log!(Level::Error, "{}: {}", args.filename, e);
+            mt_flush!().unwrap();
+            std::process::exit(1);
+        }
+    };
 
-    let (_, result) = task_1(field.clone(), '@');
+    let (_, result) = task_1(field.clone(), &rule);
     mt_log!(Level::Info, "Result Part 1: {}", result);
 
-    let (_, result) = task_2(field, '@');
-    mt_log!(Level::Info, "Result Part 2: {}", result);
+    let simulation = task_2(field.clone(), &rule);
+    mt_log!(Level::Debug, "Removed per round: {:?}", simulation.rounds);
+    mt_log!(
+        Level::Info,
+        "Result Part 2: {}",
+        simulation.rounds.iter().sum::<i32>()
+    );
+
+    if !args.no_output {
+        std::fs::write(
+            &args.output,
+            Field::to_string(Field(simulation.field.clone())),
+        )
+        .expect("Error writing file");
+        if let Some(directory) = &args.frames {
+            frames::write_frames(
+                directory,
+                &field,
+                &simulation,
+                rule.object,
+                args.image_format,
+                args.scale,
+            )
+            .expect("Error writing frames");
+        }
+    }
 
     mt_flush!().unwrap();
 }
 
 fn get_adjacent_indices(
     index: (usize, usize),
+    offsets: &[(isize, isize)],
     max_row: isize,
     max_col: isize,
 ) -> Vec<(usize, usize)> {
     let (row, col) = index;
     let mut a_indexes: Vec<(usize, usize)> = Vec::new();
-    for i in -1isize..2 {
-        for j in -1isize..2 {
-            let new_row: isize = isize::try_from(row).expect("couldnt fit row into isize") + i;
-            let new_col: isize = isize::try_from(col).expect("couldnt fit col into isize") + j;
-            if !(0 <= new_row && new_row < max_row && 0 <= new_col && new_col < max_col)
-                || (new_row == row as isize && new_col == col as isize)
-            {
-                continue;
-            }
-            a_indexes.push((new_row as usize, new_col as usize));
+    for (i, j) in offsets {
+        let new_row: isize = isize::try_from(row).expect("couldnt fit row into isize") + i;
+        let new_col: isize = isize::try_from(col).expect("couldnt fit col into isize") + j;
+        if !(0 <= new_row && new_row < max_row && 0 <= new_col && new_col < max_col) {
+            continue;
         }
+        a_indexes.push((new_row as usize, new_col as usize));
     }
     a_indexes
 }
 
 fn find_number_neighbours(
     field: &[Vec<char>],
-    object: char,
+    rule: &Rule,
     index: (usize, usize),
     max_row: isize,
     max_col: isize,
@@ -79,7 +276,8 @@ fn find_number_neighbours(
     let (row, col) = index;
     let mut count = 0;
     // loop through neighbouhrs
-    let a_index: Vec<(usize, usize)> = get_adjacent_indices((row, col), max_row, max_col);
+    let a_index: Vec<(usize, usize)> =
+        get_adjacent_indices((row, col), &rule.offsets, max_row, max_col);
     mt_log!(
         Level::Debug,
         "Index {},{} has a_index: {:?}",
@@ -88,51 +286,170 @@ fn find_number_neighbours(
         a_index
     );
     for (a_i, a_j) in a_index {
-        if field[a_i][a_j] == object {
+        if field[a_i][a_j] == rule.object {
             count += 1;
         }
     }
     count
 }
 
-fn task_1(field: Vec<Vec<char>>, object: char) -> (Vec<Vec<char>>, i32) {
+fn task_1(field: Vec<Vec<char>>, rule: &Rule) -> (Vec<Vec<char>>, i32) {
     let mut count = 0;
     let max_row = field.len();
     let max_col = field[0].len();
     let mut result_field = field.clone();
     for (i, line) in field.iter().enumerate() {
         for (j, c) in line.iter().enumerate() {
-            if *c != object {
+            if *c != rule.object {
                 continue;
             }
-            if find_number_neighbours(&field, object, (i, j), max_row as isize, max_col as isize)
-                < 4
-            {
+            if rule.matches(find_number_neighbours(
+                &field,
+                rule,
+                (i, j),
+                max_row as isize,
+                max_col as isize,
+            )) {
                 result_field[i][j] = 'x';
-                mt_log!(
-                    Level::Debug,
-                    "Index: {},{} has less than 4 neighbours",
-                    i,
-                    j
-                );
+                mt_log!(Level::Debug, "Index: {},{} matches the rule", i, j);
                 count += 1;
             }
         }
     }
-    std::fs::write("output.txt", Field::to_string(Field(result_field.clone())))
-        .expect("Error writing file");
     (result_field, count)
 }
 
-fn task_2(mut field: Vec<Vec<char>>, object: char) -> (Vec<Vec<char>>, i32) {
-    let mut count = 0;
-    let mut result;
-    loop {
-        (field, result) = task_1(field, object);
-        if result == 0 {
+// removes matching rolls round after round. Each roll's neighbour count is computed
+// once and then only decremented when a neighbour gets removed, so only neighbours of
+// removed rolls have to be checked again for the next round.
+struct Simulation {
+    field: Vec<Vec<char>>,
+    rounds: Vec<i32>,
+    // round a cell was removed in, 0 if it never was
+    removed_in: Vec<Vec<usize>>,
+}
+
+fn task_2(mut field: Vec<Vec<char>>, rule: &Rule) -> Simulation {
+    let max_row = field.len();
+    let max_col = field[0].len();
+    let mut neighbours: Vec<Vec<i32>> = vec![vec![0; max_col]; max_row];
+    let mut queue: VecDeque<(usize, usize)> = VecDeque::new();
+    for (i, line) in field.iter().enumerate() {
+        for (j, c) in line.iter().enumerate() {
+            if *c != rule.object {
+                continue;
+            }
+            neighbours[i][j] =
+                find_number_neighbours(&field, rule, (i, j), max_row as isize, max_col as isize);
+            if rule.matches(neighbours[i][j]) {
+                queue.push_back((i, j));
+            }
+        }
+    }
+
+    // the rolls that count a roll at p as a neighbour are at p - offset
+    let counted_by: Vec<(isize, isize)> = rule.offsets.iter().map(|&(i, j)| (-i, -j)).collect();
+    // round in which a cell was last queued, so it is queued at most once per round
+    let mut queued_in: Vec<Vec<usize>> = vec![vec![0; max_col]; max_row];
+    let mut removed_in: Vec<Vec<usize>> = vec![vec![0; max_col]; max_row];
+    let mut rounds: Vec<i32> = Vec::new();
+    while !queue.is_empty() {
+        let round = rounds.len() + 1;
+        let mut next_round: VecDeque<(usize, usize)> = VecDeque::new();
+        let mut removed = 0;
+        if rule.removal == Removal::Simultaneous {
+            // the rule may not be monotone, check everything against the state of the last round
+            queue.retain(|&(i, j)| field[i][j] == rule.object && rule.matches(neighbours[i][j]));
+            for &(i, j) in queue.iter() {
+                field[i][j] = 'x';
+            }
+        }
+        while let Some((i, j)) = queue.pop_front() {
+            if rule.removal == Removal::Sequential {
+                if field[i][j] != rule.object || !rule.matches(neighbours[i][j]) {
+                    continue;
+                }
+                field[i][j] = 'x';
+            }
+            removed_in[i][j] = round;
+            removed += 1;
+            for (a_i, a_j) in
+                get_adjacent_indices((i, j), &counted_by, max_row as isize, max_col as isize)
+            {
+                if field[a_i][a_j] != rule.object {
+                    continue;
+                }
+                neighbours[a_i][a_j] -= 1;
+                if queued_in[a_i][a_j] != round && rule.matches(neighbours[a_i][a_j]) {
+                    queued_in[a_i][a_j] = round;
+                    next_round.push_back((a_i, a_j));
+                }
+            }
+        }
+        if removed == 0 {
             break;
         }
-        count += result;
+        rounds.push(removed);
+        mt_log!(Level::Debug, "Round {} removed {}", round, removed);
+        queue = next_round;
+    }
+    Simulation {
+        field,
+        rounds,
+        removed_in,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn rule(offsets: &[(isize, isize)], threshold: i32, comparison: Comparison) -> Rule {
+        Rule {
+            offsets: offsets.to_vec(),
+            threshold,
+            comparison,
+            object: '@',
+            removal: Removal::Simultaneous,
+        }
+    }
+
+    // removal counts of every round, found by running task_1 until nothing changes
+    fn repeated_task_1(mut field: Vec<Vec<char>>, rule: &Rule) -> Vec<i32> {
+        let mut rounds = Vec::new();
+        loop {
+            let (next, removed) = task_1(field, rule);
+            if removed == 0 {
+                return rounds;
+            }
+            rounds.push(removed);
+            field = next;
+        }
+    }
+
+    #[test]
+    fn asymmetric_neighbourhood_removes_one_roll_per_round() {
+        let field = vec!["@@@".chars().collect()];
+        let rule = rule(&[(0, 1)], 1, Comparison::Lt);
+        let simulation = task_2(field.clone(), &rule);
+        assert_eq!(simulation.rounds, [1, 1, 1]);
+        assert_eq!(simulation.rounds, repeated_task_1(field, &rule));
+    }
+
+    proptest! {
+        #[test]
+        fn task_2_matches_repeated_task_1(
+            field in prop::collection::vec(
+                prop::collection::vec(prop_oneof![Just('@'), Just('.')], 6),
+                1..7,
+            ),
+            offsets in prop::collection::vec((-2isize..=2, -2isize..=2), 1..6),
+            threshold in 0i32..4,
+            comparison in prop_oneof![Just(Comparison::Lt), Just(Comparison::Le)],
+        ) {
+            let rule = rule(&offsets, threshold, comparison);
+            prop_assert_eq!(task_2(field.clone(), &rule).rounds, repeated_task_1(field, &rule));
+        }
     }
-    (field, count)
 }
@@ -1,114 +1,459 @@
-use std::collections::HashSet;
-
+use clap::{Parser, Subcommand};
 use mt_logger::*;
+use utils::input::Input;
+
+#[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// file with the fresh ranges, a blank line and the available ids
+    #[arg(required = true)]
+    filename: Option<String>,
+    /// read the ids from this file instead, '-' reads them from stdin
+    #[arg(long)]
+    queries: Option<String>,
+    /// print every id together with the ranges that cover it
+    #[arg(long)]
+    report: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct Database {
+    /// range database, the ranges may be followed by a blank line and ids
+    database: String,
+    /// save the result here instead of overwriting the database
+    #[arg(long)]
+    output: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// add a range of fresh ids
+    Add {
+        #[command(flatten)]
+        database: Database,
+        #[arg(value_parser = parse_range_arg)]
+        range: (u128, u128),
+    },
+    /// remove a range that is listed in the database
+    Remove {
+        #[command(flatten)]
+        database: Database,
+        #[arg(value_parser = parse_range_arg)]
+        range: (u128, u128),
+    },
+    /// mark every id in a range as spoiled
+    Subtract {
+        #[command(flatten)]
+        database: Database,
+        #[arg(value_parser = parse_range_arg)]
+        range: (u128, u128),
+    },
+    /// fresh ids of the first database that are not fresh in the second
+    Diff {
+        database: String,
+        other: String,
+        /// save the difference here instead of printing it
+        #[arg(long)]
+        output: Option<String>,
+    },
+}
 
 struct IngredientDB {
+    // ranges as given in the input
     ranges: Vec<(u128, u128)>,
+    // indices into `ranges` sorted by start, with the largest end seen so far
+    by_start: Vec<usize>,
+    max_end: Vec<u128>,
+    // sorted, disjoint and non adjacent
+    merged: Vec<(u128, u128)>,
 }
+
 impl IngredientDB {
-    fn from_string(s: &str) -> IngredientDB {
+    fn from_string(s: &str) -> Result<IngredientDB, String> {
+        let ranges = s
+            .lines()
+            .enumerate()
+            .map(|(i, line)| {
+                parse_range(line)
+                    .ok_or_else(|| format!("Invalid range '{}' on line {}", line, i + 1))
+            })
+            .collect::<Result<Vec<(u128, u128)>, String>>()?;
+        Ok(IngredientDB::from_ranges(ranges))
+    }
+
+    fn from_ranges(ranges: Vec<(u128, u128)>) -> IngredientDB {
+        let mut by_start: Vec<usize> = (0..ranges.len()).collect();
+        by_start.sort_by_key(|&i| ranges[i]);
+
+        let mut max_end = Vec::with_capacity(ranges.len());
+        let mut merged: Vec<(u128, u128)> = Vec::new();
+        for &i in &by_start {
+            let (a, b) = ranges[i];
+            max_end.push(max_end.last().map_or(b, |&end: &u128| end.max(b)));
+            match merged.last_mut() {
+                Some((_, end)) if a <= end.saturating_add(1) => *end = (*end).max(b),
+                _ => merged.push((a, b)),
+            }
+        }
         IngredientDB {
-            ranges: s
-                .lines()
-                .map(|line| {
-                    let mut parts = line.split("-");
-                    let a = parts.next().unwrap().parse::<u128>().unwrap();
-                    let b = parts.next().unwrap().parse::<u128>().unwrap();
-                    (a, b)
-                })
-                .collect(),
+            ranges,
+            by_start,
+            max_end,
+            merged,
         }
     }
 
     fn id_exists(&self, id: u128) -> bool {
-        for &(a, b) in &self.ranges {
-            if a <= id && id <= b {
-                return true;
+        let i = self.merged.partition_point(|&(a, _)| a <= id);
+        i > 0 && id <= self.merged[i - 1].1
+    }
+
+    /// Original ranges containing `id`, in input order.
+    fn covering_ranges(&self, id: u128) -> Vec<(u128, u128)> {
+        let mut i = self
+            .by_start
+            .partition_point(|&index| self.ranges[index].0 <= id);
+        let mut covering = Vec::new();
+        // no range starting before i - 1 reaches id once the running max end is below it
+        while i > 0 && self.max_end[i - 1] >= id {
+            i -= 1;
+            if self.ranges[self.by_start[i]].1 >= id {
+                covering.push(self.by_start[i]);
             }
         }
-        false
-    }
-
-    fn num_fresh_ids(&self) -> u128 {
-        let mut sorted_ranges: Vec<(u128, u128)> = self.ranges.clone();
-        sorted_ranges.sort();
-
-        let mut filtered_ranges: Vec<(u128, u128)>;
-        loop {
-            filtered_ranges = Vec::new();
-            let mut merges = false;
-            let mut skip = false;
-            for i in 0..sorted_ranges.len() - 1 {
-                let (a, b) = sorted_ranges[i];
-                let (a2, b2) = sorted_ranges[i + 1];
-                if skip {
-                    skip = false;
-                    continue;
-                }
-                mt_log!(Level::Debug, "{},{}, {},{}", a, b, a2, b2);
-                if b < a2 {
-                    filtered_ranges.push((a, b));
-                    if i == (sorted_ranges.len() - 2) {
-                        filtered_ranges.push((a2, b2));
-                    }
-                    continue;
-                }
-                merges = true;
-                mt_log!(Level::Debug, "MERGE HAPPENING");
-                if b >= b2 {
-                    filtered_ranges.push((a, b));
-                    skip = true;
-                } else {
-                    filtered_ranges.push((a, b2));
-                }
-            }
-            if !merges {
+        covering.sort();
+        covering
+            .into_iter()
+            .map(|index| self.ranges[index])
+            .collect()
+    }
+
+    /// `None` if the count does not fit into u128, i.e. every id is fresh.
+    fn num_fresh_ids(&self) -> Option<u128> {
+        self.merged.iter().try_fold(0u128, |count, (a, b)| {
+            (b - a).checked_add(1)?.checked_add(count)
+        })
+    }
+
+    fn add(&self, range: (u128, u128)) -> IngredientDB {
+        let mut ranges = self.merged.clone();
+        ranges.push(range);
+        IngredientDB::from_ranges(ranges)
+    }
+
+    fn remove(&self, range: (u128, u128)) -> Result<IngredientDB, String> {
+        let Some(index) = self.ranges.iter().position(|&r| r == range) else {
+            return Err(format!(
+                "Range {}-{} is not in the database",
+                range.0, range.1
+            ));
+        };
+        let mut ranges = self.ranges.clone();
+        ranges.remove(index);
+        Ok(IngredientDB::from_ranges(ranges))
+    }
+
+    fn subtract(&self, range: (u128, u128)) -> IngredientDB {
+        IngredientDB::from_ranges(difference(&self.merged, &[range]))
+    }
+
+    fn difference(&self, other: &IngredientDB) -> IngredientDB {
+        IngredientDB::from_ranges(difference(&self.merged, &other.merged))
+    }
+
+    /// The merged ranges, one `a-b` per line.
+    fn format_ranges(&self) -> String {
+        self.merged
+            .iter()
+            .map(|(a, b)| format!("{}-{}\n", a, b))
+            .collect()
+    }
+}
+
+fn parse_range(s: &str) -> Option<(u128, u128)> {
+    let (a, b) = s.split_once('-')?;
+    let range: (u128, u128) = (a.trim().parse().ok()?, b.trim().parse().ok()?);
+    (range.0 <= range.1).then_some(range)
+}
+
+fn parse_range_arg(s: &str) -> Result<(u128, u128), String> {
+    parse_range(s).ok_or_else(|| format!("'{}' is not a range a-b with a <= b", s))
+}
+
+// ids of `left` that are not in `right`, both sorted and disjoint
+fn difference(left: &[(u128, u128)], right: &[(u128, u128)]) -> Vec<(u128, u128)> {
+    let mut result = Vec::new();
+    let mut j = 0;
+    for &(a, b) in left {
+        while j < right.len() && right[j].1 < a {
+            j += 1;
+        }
+        let mut start = Some(a);
+        let mut k = j;
+        while let Some(s) = start {
+            if k == right.len() || right[k].0 > b {
                 break;
             }
-            sorted_ranges = filtered_ranges.clone();
-            mt_log!(Level::Debug, "after 1 iteration: {:?}", filtered_ranges);
-            mt_log!(Level::Info, "len: {}", filtered_ranges.len());
+            let (c, d) = right[k];
+            if c > s {
+                result.push((s, c - 1));
+            }
+            start = if d < b { Some(d + 1) } else { None };
+            k += 1;
         }
-        mt_log!(
-            Level::Debug,
-            "final: {:?}, {}",
-            filtered_ranges,
-            filtered_ranges.len()
-        );
+        if let Some(s) = start {
+            result.push((s, b));
+        }
+    }
+    result
+}
 
-        let mut num_ids_fresh = 0;
-        for (a, b) in filtered_ranges {
-            num_ids_fresh += b - a + 1;
+fn parse_ids(s: &str) -> Result<Vec<u128>, String> {
+    s.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            line.trim()
+                .parse()
+                .map_err(|_| format!("Invalid id '{}' on line {}", line, i + 1))
+        })
+        .collect()
+}
+
+fn exit_with_error(message: &str) -> ! {
+    mt_log!(Level::Error, "{}", message);
+    mt_flush!().unwrap();
+    std::process::exit(1);
+}
+
+// the database and anything after its ranges, which is kept when saving
+fn load_database(filename: &str) -> (IngredientDB, String) {
+    let file_content = Input::read(filename).expect("Couldnt read file");
+    parse_database(&file_content)
+        .unwrap_or_else(|e| exit_with_error(&format!("{}: {}", filename, e)))
+}
+
+fn parse_database(content: &Input) -> Result<(IngredientDB, String), String> {
+    let sections = content.sections();
+    let ingredient_db = IngredientDB::from_string(sections.first().copied().unwrap_or(""))?;
+    Ok((
+        ingredient_db,
+        sections[1.min(sections.len())..].join("\n\n"),
+    ))
+}
+
+fn save_database(ingredient_db: &IngredientDB, rest: &str, filename: &str) {
+    let s = format_database(ingredient_db, rest).unwrap_or_else(|e| exit_with_error(&e));
+    std::fs::write(filename, s).expect("Couldnt write file");
+    mt_log!(
+        Level::Info,
+        "Saved {} ranges to {}",
+        ingredient_db.merged.len(),
+        filename
+    );
+}
+
+// blank lines separate the sections, so without ranges whatever follows them
+// would be read back as the ranges
+fn format_database(ingredient_db: &IngredientDB, rest: &str) -> Result<String, String> {
+    let mut s = ingredient_db.format_ranges();
+    if !rest.is_empty() {
+        if s.is_empty() {
+            return Err(
+                "Refusing to save a database without ranges but with ids after them, \
+it could not be loaded again"
+                    .to_string(),
+            );
         }
-        num_ids_fresh
+        s.push('\n');
+        s.push_str(rest);
+        s.push('\n');
     }
+    Ok(s)
+}
+
+fn log_fresh_ids(ingredient_db: &IngredientDB) {
+    match ingredient_db.num_fresh_ids() {
+        Some(fresh) => mt_log!(Level::Info, "Fresh ids: {}", fresh),
+        None => mt_log!(Level::Info, "Fresh ids: all 2^128"),
+    }
+}
+
+fn run_command(command: Command) {
+    let (database, result, rest) = match command {
+        Command::Add { database, range } => {
+            let (ingredient_db, rest) = load_database(&database.database);
+            (database, ingredient_db.add(range), rest)
+        }
+        Command::Remove { database, range } => {
+            let (ingredient_db, rest) = load_database(&database.database);
+            let result = ingredient_db
+                .remove(range)
+                .unwrap_or_else(|e| exit_with_error(&e));
+            (database, result, rest)
+        }
+        Command::Subtract { database, range } => {
+            let (ingredient_db, rest) = load_database(&database.database);
+            (database, ingredient_db.subtract(range), rest)
+        }
+        Command::Diff {
+            database,
+            other,
+            output,
+        } => {
+            let (ingredient_db, _) = load_database(&database);
+            let (other_db, _) = load_database(&other);
+            let result = ingredient_db.difference(&other_db);
+            log_fresh_ids(&result);
+            match output {
+                Some(output) => save_database(&result, "", &output),
+                None => print!("{}", result.format_ranges()),
+            }
+            return;
+        }
+    };
+    log_fresh_ids(&result);
+    let output = database.output.as_ref().unwrap_or(&database.database);
+    save_database(&result, &rest, output);
 }
 
 fn main() {
-    mt_new!(None, Level::Debug, OutputStream::StdOut, true);
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() < 2 {
-        mt_log!(Level::Error, "Usage: {} <input>", args[0]);
+    mt_new!(None, Level::Info, OutputStream::StdOut, true);
+    let args = Args::parse();
+    if let Some(command) = args.command {
+        run_command(command);
         mt_flush!().unwrap();
-        std::process::exit(1);
+        return;
     }
-    let filename = &args[1];
-    let file_content = std::fs::read_to_string(filename).expect("Couldnt read file");
-    let ingredient_db_string: Vec<&str> = file_content.split("\r\n\r\n").collect();
+    let filename = args.filename.unwrap();
+    let file_content = Input::read(&filename).expect("Couldnt read file");
+    let ingredient_db_string = file_content.sections();
     mt_log!(Level::Debug, "{:?}", ingredient_db_string);
-    let ingredient_db = IngredientDB::from_string(ingredient_db_string.first().unwrap());
+
+    // with --queries the ids in the database file, if any, are ignored
+    let expected_sections = if args.queries.is_some() { 1..=2 } else { 2..=2 };
+    if !expected_sections.contains(&ingredient_db_string.len()) {
+        exit_with_error(&format!(
+            "Expected {} section(s) in {}, found {}",
+            if args.queries.is_some() {
+                "1 or 2"
+            } else {
+                "2"
+            },
+            filename,
+            ingredient_db_string.len()
+        ));
+    }
+    let ingredient_db =
+        IngredientDB::from_string(ingredient_db_string[0]).unwrap_or_else(|e| exit_with_error(&e));
+    mt_log!(Level::Debug, "merged: {:?}", ingredient_db.merged);
+
+    let queries = match args.queries.as_deref() {
+        None => Input::from_string(ingredient_db_string[1]),
+        Some("-") => Input::from_string(
+            &std::io::read_to_string(std::io::stdin()).expect("Couldnt read stdin"),
+        ),
+        Some(filename) => Input::read(filename).expect("Couldnt read queries"),
+    };
+    let ids = parse_ids(queries.as_str()).unwrap_or_else(|e| exit_with_error(&e));
 
     let mut count = 0;
-    for id_str in ingredient_db_string.get(1).unwrap().lines() {
-        if ingredient_db.id_exists(id_str.parse::<u128>().unwrap()) {
+    for &id in &ids {
+        let fresh = ingredient_db.id_exists(id);
+        if fresh {
             count += 1;
         }
+        if args.report {
+            let covering: Vec<String> = ingredient_db
+                .covering_ranges(id)
+                .iter()
+                .map(|(a, b)| format!("{}-{}", a, b))
+                .collect();
+            if fresh {
+                mt_log!(Level::Info, "{}: fresh in {}", id, covering.join(", "));
+            } else {
+                mt_log!(Level::Info, "{}: spoiled", id);
+            }
+        }
     }
     mt_log!(Level::Info, "Result Part 1: {}", count);
-    mt_log!(
-        Level::Info,
-        "Result Part 2: {}",
-        ingredient_db.num_fresh_ids()
-    );
+    match ingredient_db.num_fresh_ids() {
+        Some(fresh) => mt_log!(Level::Info, "Result Part 2: {}", fresh),
+        None => exit_with_error("Every u128 id is fresh, the count does not fit into u128"),
+    }
     mt_flush!().unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_matches_scanning_the_ranges() {
+        let ranges = vec![
+            (3, 5),
+            (10, 14),
+            (16, 20),
+            (12, 18),
+            (1, 1),
+            (6, 6),
+            (30, 40),
+        ];
+        let db = IngredientDB::from_ranges(ranges.clone());
+        assert_eq!(db.merged, [(1, 1), (3, 6), (10, 20), (30, 40)]);
+        for id in 0..45 {
+            let covering: Vec<(u128, u128)> = ranges
+                .iter()
+                .copied()
+                .filter(|&(a, b)| a <= id && id <= b)
+                .collect();
+            assert_eq!(db.id_exists(id), !covering.is_empty());
+            assert_eq!(db.covering_ranges(id), covering);
+        }
+        assert_eq!(db.num_fresh_ids(), Some(1 + 4 + 11 + 11));
+    }
+
+    #[test]
+    fn difference_matches_set_difference() {
+        let left = [(0, 4), (6, 20), (25, 25), (30, u128::MAX)];
+        let right = [(2, 3), (5, 7), (10, 10), (12, 26), (40, u128::MAX)];
+        assert_eq!(
+            difference(&left, &right),
+            [(0, 1), (4, 4), (8, 9), (11, 11), (30, 39)]
+        );
+        let db = IngredientDB::from_ranges(left.to_vec());
+        assert_eq!(db.subtract((0, u128::MAX)).num_fresh_ids(), Some(0));
+        assert_eq!(db.add((0, u128::MAX)).num_fresh_ids(), None);
+        assert_eq!(
+            db.add((0, u128::MAX)).subtract((7, 7)).num_fresh_ids(),
+            Some(u128::MAX)
+        );
+        assert_eq!(
+            db.add((5, 5)).add((21, 24)).merged,
+            [(0, 25), (30, u128::MAX)]
+        );
+        assert!(db.remove((6, 19)).is_err());
+        assert_eq!(
+            db.remove((6, 20)).unwrap().num_fresh_ids(),
+            Some(5 + 1 + (u128::MAX - 29))
+        );
+    }
+
+    #[test]
+    fn saved_databases_load_again() {
+        let db = IngredientDB::from_ranges(vec![(3, 5), (10, 14), (12, 18)]);
+        let round_trip = |db: &IngredientDB, rest: &str| {
+            let saved = format_database(db, rest).unwrap();
+            let (loaded, loaded_rest) = parse_database(&Input::from_string(&saved)).unwrap();
+            assert_eq!(loaded.merged, db.merged);
+            assert_eq!(loaded_rest, rest);
+        };
+        round_trip(&db, "");
+        round_trip(&db, "1\n5\n8");
+        let empty = db.subtract((0, u128::MAX));
+        round_trip(&empty, "");
+        assert!(format_database(&empty, "1\n5\n8").is_err());
+    }
+}
@@ -1,156 +1,509 @@
-use core::num;
+use std::fmt;
 
+use clap::Parser;
 use mt_logger::*;
-use utils::filter_non_relevant_chars;
+use num_bigint::BigInt;
+use utils::input::Input;
 
-#[derive(Clone, Debug)]
+mod worksheet;
+
+use worksheet::{Direction, Reading, Worksheet};
+
+#[derive(Parser, Debug)]
+struct Args {
+    /// worksheet with the operands in rows and the operators in the last line
+    filename: String,
+    /// evaluate with arbitrary precision instead of checked i128 arithmetic
+    #[arg(long)]
+    bigint: bool,
+    /// solve a single reading of the worksheet instead of both parts,
+    /// defaults to rows
+    #[arg(long, value_enum)]
+    reading: Option<Reading>,
+    /// horizontal reading direction of a single reading, defaults to ltr
+    #[arg(long, value_enum)]
+    direction: Option<Direction>,
+    /// list the operands, operator and result of every problem
+    #[arg(long)]
+    report: bool,
+    /// print the worksheet with a row of results under the problems
+    #[arg(long)]
+    render: bool,
+}
+
+/// Binary operators, a problem applies its operator from left to right:
+/// `a - b - c` is `(a - b) - c` and `a ^ b ^ c` is `(a ^ b) ^ c`.
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum Operator {
-    Multiplication,
     Addition,
+    Subtraction,
+    Multiplication,
+    /// rounds towards zero
+    Division,
+    Exponent,
+    Minimum,
+    Maximum,
 }
 
-#[derive(Debug)]
-struct MathProblem {
-    tasks: Vec<(Vec<i128>, Operator)>,
+impl Operator {
+    const ALL: [Operator; 7] = [
+        Operator::Addition,
+        Operator::Subtraction,
+        Operator::Multiplication,
+        Operator::Division,
+        Operator::Exponent,
+        Operator::Minimum,
+        Operator::Maximum,
+    ];
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Operator::Addition => "+",
+            Operator::Subtraction => "-",
+            Operator::Multiplication => "*",
+            Operator::Division => "/",
+            Operator::Exponent => "^",
+            Operator::Minimum => "min",
+            Operator::Maximum => "max",
+        }
+    }
+
+    fn from_token(token: &str) -> Option<Operator> {
+        Operator::ALL
+            .into_iter()
+            .find(|operator| operator.symbol() == token)
+    }
+
+    fn apply(self, a: i128, b: i128) -> Result<i128, EvalError> {
+        let result = match self {
+            Operator::Addition => a.checked_add(b),
+            Operator::Subtraction => a.checked_sub(b),
+            Operator::Multiplication => a.checked_mul(b),
+            Operator::Division if b == 0 => return Err(EvalError::DivisionByZero),
+            Operator::Division => a.checked_div(b),
+            Operator::Exponent => {
+                let exponent =
+                    u32::try_from(b).map_err(|_| EvalError::InvalidExponent(b.to_string()))?;
+                a.checked_pow(exponent)
+            }
+            Operator::Minimum => Some(a.min(b)),
+            Operator::Maximum => Some(a.max(b)),
+        };
+        result.ok_or(EvalError::Overflow)
+    }
+
+    fn apply_big(self, a: BigInt, b: &BigInt) -> Result<BigInt, EvalError> {
+        Ok(match self {
+            Operator::Addition => a + b,
+            Operator::Subtraction => a - b,
+            Operator::Multiplication => a * b,
+            Operator::Division if *b == BigInt::ZERO => return Err(EvalError::DivisionByZero),
+            Operator::Division => a / b,
+            Operator::Exponent => {
+                let exponent =
+                    u32::try_from(b).map_err(|_| EvalError::InvalidExponent(b.to_string()))?;
+                a.pow(exponent)
+            }
+            Operator::Minimum => a.min(b.clone()),
+            Operator::Maximum => a.max(b.clone()),
+        })
+    }
 }
 
-impl MathProblem {
-    fn from_str_part1(s: &str) -> MathProblem {
-        let num_lines: usize = s.lines().collect::<Vec<&str>>().len();
-        let mut tasks: Vec<(Vec<i128>, Operator)> = Vec::new();
-
-        for (i, line) in s.lines().enumerate() {
-            mt_log!(Level::Debug, "Original Line: {:?}", line);
-            let filtered_line = filter_non_relevant_chars(line);
-            mt_log!(Level::Debug, "Filtered Line: {:?}", filtered_line);
-            if i == 0 {
-                tasks = vec![(Vec::new(), Operator::Addition); filtered_line.len()];
+#[derive(Debug, PartialEq)]
+enum EvalError {
+    Overflow,
+    DivisionByZero,
+    InvalidExponent(String),
+}
+
+#[derive(Debug, PartialEq)]
+enum WorksheetError {
+    UnknownOperator { token: String, column: usize },
+    MissingOperator { column: usize },
+    InvalidNumber { token: String, column: usize },
+    NoOperands { problem: usize },
+    Eval { problem: usize, error: EvalError },
+}
+
+impl fmt::Display for WorksheetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WorksheetError::UnknownOperator { token, column } => {
+                write!(f, "Unknown operator '{}' in column {}", token, column + 1)
             }
-            if i == num_lines - 1 {
-                let operators: Vec<Operator> = filtered_line
-                    .iter()
-                    .map(|number_str| match number_str.trim() {
-                        "*" => Operator::Multiplication,
-                        _ => Operator::Addition,
-                    })
-                    .collect();
-                for (j, operator) in operators.iter().enumerate() {
-                    tasks[j].1 = operator.clone();
-                }
-            } else {
-                let numbers: Vec<i128> = filtered_line
-                    .iter()
-                    .map(|number_str| {
-                        number_str
-                            .trim()
-                            .parse::<i128>()
-                            .unwrap_or_else(|_| panic!("could not parse: {}", number_str))
-                    })
-                    .collect();
-                for (j, number) in numbers.iter().enumerate() {
-                    tasks[j].0.push(*number);
-                }
+            WorksheetError::MissingOperator { column } => {
+                write!(
+                    f,
+                    "Problem starting in column {} has no operator",
+                    column + 1
+                )
             }
-        }
-        mt_log!(Level::Debug, "{:?}", tasks);
-        MathProblem { tasks }
-    }
-    fn from_str_part2(s: &str) -> MathProblem {
-        let lines: Vec<&str> = s.lines().collect();
-        let mut tasks: Vec<(Vec<i128>, Operator)> = Vec::new();
-        let mut numbers: Vec<i128> = Vec::new();
-        let mut number_str: Vec<char> = Vec::new();
-        for i in (0..lines[0].chars().collect::<Vec<char>>().len()).rev() {
-            for line in &lines {
-                let c = line.chars().nth(i).unwrap();
-                match c {
-                    ' ' => {
-                        if !number_str.is_empty() {
-                            numbers.push(
-                                number_str
-                                    .into_iter()
-                                    .collect::<String>()
-                                    .parse()
-                                    .expect("couldnt parse"),
-                            );
-                            number_str = Vec::new();
-                        }
-                    }
-                    '*' => {
-                        if !number_str.is_empty() {
-                            numbers.push(
-                                number_str
-                                    .into_iter()
-                                    .collect::<String>()
-                                    .parse()
-                                    .expect("couldnt parse"),
-                            );
-                            number_str = Vec::new();
-                        }
-                        tasks.push((numbers, Operator::Multiplication));
-                        numbers = Vec::new();
-                    }
-                    '+' => {
-                        if !number_str.is_empty() {
-                            numbers.push(
-                                number_str
-                                    .into_iter()
-                                    .collect::<String>()
-                                    .parse()
-                                    .expect("couldnt parse"),
-                            );
-                            number_str = Vec::new();
-                        }
-                        tasks.push((numbers, Operator::Addition));
-                        numbers = Vec::new();
-                    }
-                    _ => {
-                        number_str.push(c);
-                    }
-                }
+            WorksheetError::InvalidNumber { token, column } => {
+                write!(f, "Invalid number '{}' in column {}", token, column + 1)
             }
-        }
-        MathProblem { tasks }
-    }
-    fn solve(&self) -> i128 {
-        let mut total_sum = 0;
-        for task in &self.tasks {
-            total_sum += match task.1 {
-                Operator::Addition => {
-                    let mut result = 0;
-                    for number in &task.0 {
-                        result += number;
-                    }
-                    result
-                }
-                Operator::Multiplication => {
-                    let mut result = 1;
-                    for number in &task.0 {
-                        result *= number;
+            WorksheetError::NoOperands { problem } => {
+                write!(f, "Problem {} has no operands", problem + 1)
+            }
+            WorksheetError::Eval { problem, error } => {
+                write!(f, "Problem {}: ", problem + 1)?;
+                match error {
+                    EvalError::Overflow => write!(f, "overflow, try --bigint"),
+                    EvalError::DivisionByZero => write!(f, "division by zero"),
+                    EvalError::InvalidExponent(exponent) => {
+                        write!(f, "exponent {} is negative or too large", exponent)
                     }
-                    result
                 }
             }
         }
-        total_sum
     }
 }
 
-fn main() {
-    mt_new!(None, Level::Debug, OutputStream::StdOut, true);
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() < 2 {
-        mt_log!(Level::Error, "Usage: {} <inputfile>", args[0]);
-        mt_flush!().unwrap();
-        std::process::exit(1);
+fn parse_operator(token: &str, column: usize) -> Result<Operator, WorksheetError> {
+    Operator::from_token(token).ok_or_else(|| WorksheetError::UnknownOperator {
+        token: token.to_string(),
+        column,
+    })
+}
+
+fn parse_number(token: &str, column: usize) -> Result<i128, WorksheetError> {
+    token.parse().map_err(|_| WorksheetError::InvalidNumber {
+        token: token.to_string(),
+        column,
+    })
+}
+
+#[derive(Debug, PartialEq)]
+struct Task {
+    numbers: Vec<i128>,
+    operator: Operator,
+    // start..end columns of the problem on the worksheet
+    columns: (usize, usize),
+}
+
+impl Task {
+    fn evaluate(&self, problem: usize) -> Result<i128, WorksheetError> {
+        let (first, rest) = self
+            .numbers
+            .split_first()
+            .ok_or(WorksheetError::NoOperands { problem })?;
+        let mut result = *first;
+        for &number in rest {
+            result = self
+                .operator
+                .apply(result, number)
+                .map_err(|error| WorksheetError::Eval { problem, error })?;
+        }
+        Ok(result)
     }
-    let filename = &args[1];
-    let file_content = std::fs::read_to_string(filename).expect("Could not read file");
-    let math_problem = MathProblem::from_str_part1(&file_content);
-    mt_log!(Level::Info, "Result Part 1: {}", math_problem.solve());
-    let math_problem = MathProblem::from_str_part2(&file_content);
+
+    fn evaluate_big(&self, problem: usize) -> Result<BigInt, WorksheetError> {
+        let (first, rest) = self
+            .numbers
+            .split_first()
+            .ok_or(WorksheetError::NoOperands { problem })?;
+        let mut result = BigInt::from(*first);
+        for &number in rest {
+            result = self
+                .operator
+                .apply_big(result, &BigInt::from(number))
+                .map_err(|error| WorksheetError::Eval { problem, error })?;
+        }
+        Ok(result)
+    }
+
+    /// The problem written out, e.g. `356 * 24 * 1`.
+    fn expression(&self) -> String {
+        self.numbers
+            .iter()
+            .map(|number| number.to_string())
+            .collect::<Vec<String>>()
+            .join(&format!(" {} ", self.operator.symbol()))
+    }
+}
+
+#[derive(Debug)]
+struct MathProblem {
+    tasks: Vec<Task>,
+}
+
+impl MathProblem {
+    fn from_worksheet(
+        worksheet: &Worksheet,
+        reading: Reading,
+        direction: Direction,
+    ) -> Result<MathProblem, WorksheetError> {
+        Ok(MathProblem {
+            tasks: worksheet.read(reading, direction)?,
+        })
+    }
+    /// The result of every problem and their checked total.
+    fn results(&self) -> Result<(Vec<i128>, i128), WorksheetError> {
+        let mut total_sum: i128 = 0;
+        let mut results = Vec::with_capacity(self.tasks.len());
+        for (problem, task) in self.tasks.iter().enumerate() {
+            let result = task.evaluate(problem)?;
+            total_sum = total_sum.checked_add(result).ok_or(WorksheetError::Eval {
+                problem,
+                error: EvalError::Overflow,
+            })?;
+            results.push(result);
+        }
+        Ok((results, total_sum))
+    }
+
+    fn results_big(&self) -> Result<(Vec<BigInt>, BigInt), WorksheetError> {
+        let results = self
+            .tasks
+            .iter()
+            .enumerate()
+            .map(|(problem, task)| task.evaluate_big(problem))
+            .collect::<Result<Vec<BigInt>, WorksheetError>>()?;
+        let total = results.iter().sum();
+        Ok((results, total))
+    }
+}
+
+// result of every problem and the total, as text so both number modes look the same
+fn solve(
+    math_problem: &MathProblem,
+    bigint: bool,
+) -> Result<(Vec<String>, String), WorksheetError> {
     mt_log!(Level::Debug, "{:?}", math_problem);
+    if bigint {
+        let (results, total) = math_problem.results_big()?;
+        Ok((
+            results.iter().map(|r| r.to_string()).collect(),
+            total.to_string(),
+        ))
+    } else {
+        let (results, total) = math_problem.results()?;
+        Ok((
+            results.iter().map(|r| r.to_string()).collect(),
+            total.to_string(),
+        ))
+    }
+}
+
+fn main() {
+    mt_new!(None, Level::Info, OutputStream::StdOut, true);
+    let args = Args::parse();
+    let file_content = Input::read(&args.filename).expect("Could not read file");
+    let worksheet = Worksheet::from_string(file_content.as_str());
+    mt_log!(Level::Debug, "{:?}", worksheet.blocks());
+
+    let readings = if args.reading.is_some() || args.direction.is_some() {
+        vec![(
+            "Custom reading".to_string(),
+            args.reading.unwrap_or(Reading::Rows),
+            args.direction.unwrap_or(Direction::LeftToRight),
+        )]
+    } else {
+        vec![
+            ("Part 1".to_string(), Reading::Rows, Direction::LeftToRight),
+            (
+                "Part 2".to_string(),
+                Reading::Columns,
+                Direction::RightToLeft,
+            ),
+        ]
+    };
+    let mut failed = false;
+    for (name, reading, direction) in readings {
+        let solved =
+            MathProblem::from_worksheet(&worksheet, reading, direction).and_then(|math_problem| {
+                let (results, total) = solve(&math_problem, args.bigint)?;
+                Ok((math_problem, results, total))
+            });
+        let (math_problem, results, total) = match solved {
+            Ok(solved) => solved,
+            Err(e) => {
+                mt_log!(Level::Error, "{}: {}", name, e);
+                failed = true;
+                continue;
+            }
+        };
+        if args.report {
+            for (problem, (task, result)) in math_problem.tasks.iter().zip(&results).enumerate() {
+                mt_log!(
+                    Level::Info,
+                    "{} problem {} (columns {}-{}): {} = {}",
+                    name,
+                    problem + 1,
+                    task.columns.0 + 1,
+                    task.columns.1,
+                    task.expression(),
+                    result
+                );
+            }
+        }
+        if args.render {
+            let results: Vec<((usize, usize), String)> = math_problem
+                .tasks
+                .iter()
+                .map(|task| task.columns)
+                .zip(results)
+                .collect();
+            mt_log!(Level::Info, "{}:\n{}", name, worksheet.render(&results));
+        }
+        mt_log!(Level::Info, "Result {}: {}", name, total);
+    }
     mt_flush!().unwrap();
-    mt_log!(Level::Info, "Result Part 2: {}", math_problem.solve());
-    mt_flush!().unwrap();
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(numbers: Vec<i128>, operator: Operator) -> Task {
+        Task {
+            numbers,
+            operator,
+            columns: (0, 0),
+        }
+    }
+
+    #[test]
+    fn operators_fold_left_to_right_and_check_overflow() {
+        let problem = MathProblem {
+            tasks: vec![
+                task(vec![20, 5, 3], Operator::Subtraction),
+                task(vec![2, 3, 2], Operator::Exponent),
+                task(vec![-7, 2], Operator::Division),
+                task(vec![4, 9, 1], Operator::Minimum),
+                task(vec![4, 9, 1], Operator::Maximum),
+            ],
+        };
+        assert_eq!(problem.results(), Ok((vec![12, 64, -3, 1, 9], 83)));
+        assert_eq!(solve(&problem, true).unwrap().1, "83");
+
+        let overflow = MathProblem {
+            tasks: vec![task(vec![10, 40], Operator::Exponent)],
+        };
+        assert_eq!(
+            overflow.results(),
+            Err(WorksheetError::Eval {
+                problem: 0,
+                error: EvalError::Overflow
+            })
+        );
+        assert_eq!(
+            overflow.results_big().unwrap(),
+            (vec![BigInt::from(10).pow(40)], BigInt::from(10).pow(40))
+        );
+        // every problem fits but the total does not
+        let total_overflow = MathProblem {
+            tasks: vec![
+                task(vec![i128::MAX], Operator::Addition),
+                task(vec![1], Operator::Addition),
+            ],
+        };
+        assert_eq!(
+            solve(&total_overflow, false),
+            Err(WorksheetError::Eval {
+                problem: 1,
+                error: EvalError::Overflow
+            })
+        );
+        assert_eq!(
+            solve(&total_overflow, true).unwrap().1,
+            (BigInt::from(i128::MAX) + BigInt::from(1)).to_string()
+        );
+        assert_eq!(
+            Operator::Division.apply(1, 0),
+            Err(EvalError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn unknown_operators_report_their_column() {
+        let worksheet = Worksheet::from_string("1 2\n3 4\n+ %");
+        assert_eq!(
+            MathProblem::from_worksheet(&worksheet, Reading::Rows, Direction::LeftToRight)
+                .unwrap_err(),
+            WorksheetError::UnknownOperator {
+                token: "%".to_string(),
+                column: 2
+            }
+        );
+    }
+
+    #[test]
+    fn operators_may_be_wider_than_their_problem() {
+        let worksheet = Worksheet::from_string("12 34\n 5 6\nmax +");
+        assert_eq!(worksheet.blocks(), [(0, 2), (3, 5)]);
+        let math_problem =
+            MathProblem::from_worksheet(&worksheet, Reading::Rows, Direction::LeftToRight).unwrap();
+        assert_eq!(math_problem.tasks[0].operator, Operator::Maximum);
+        assert_eq!(math_problem.tasks[0].numbers, vec![12, 5]);
+        assert_eq!(math_problem.results(), Ok((vec![12, 40], 52)));
+        let math_problem =
+            MathProblem::from_worksheet(&worksheet, Reading::Columns, Direction::RightToLeft)
+                .unwrap();
+        assert_eq!(math_problem.tasks[1].numbers, vec![25, 1]);
+        assert_eq!(math_problem.tasks[1].operator, Operator::Maximum);
+    }
+
+    #[test]
+    fn reads_ragged_worksheets_in_every_orientation() {
+        // trailing spaces stripped by an editor
+        let worksheet = Worksheet::from_string(
+            "123 328  51 64\n 45 64  387 23\n  6 98  215 314\n*   +   *   max",
+        );
+        assert_eq!(worksheet.blocks(), [(0, 3), (4, 7), (8, 11), (12, 15)]);
+        let read = |reading, direction| {
+            MathProblem::from_worksheet(&worksheet, reading, direction)
+                .unwrap()
+                .tasks
+        };
+        assert_eq!(
+            read(Reading::Rows, Direction::LeftToRight)[3].numbers,
+            vec![64, 23, 314]
+        );
+        assert_eq!(
+            read(Reading::Rows, Direction::RightToLeft)[0].operator,
+            Operator::Maximum
+        );
+        assert_eq!(
+            read(Reading::Rows, Direction::RightToLeft)[0].numbers,
+            vec![64, 23, 314]
+        );
+        assert_eq!(
+            read(Reading::Columns, Direction::RightToLeft)[0].numbers,
+            vec![4, 431, 623]
+        );
+        assert_eq!(
+            read(Reading::Columns, Direction::LeftToRight)[0].numbers,
+            vec![1, 24, 356]
+        );
+    }
+
+    #[test]
+    fn renders_results_under_their_problems() {
+        let worksheet = Worksheet::from_string("123 328\n 45 64\n*   +");
+        let math_problem =
+            MathProblem::from_worksheet(&worksheet, Reading::Rows, Direction::LeftToRight).unwrap();
+        let (results, total) = solve(&math_problem, false).unwrap();
+        assert_eq!(total, "5927");
+        let results: Vec<((usize, usize), String)> = math_problem
+            .tasks
+            .iter()
+            .map(|task| task.columns)
+            .zip(results)
+            .collect();
+        assert_eq!(
+            worksheet.render(&results),
+            "123 328\n 45 64\n*   +\n--- ---\n5535 392"
+        );
+        // text past the last problem is kept, wide results go into the gaps
+        let worksheet = Worksheet::from_string("1 2\n3 4\n+ max");
+        assert_eq!(worksheet.blocks(), [(0, 1), (2, 3)]);
+        let results = vec![((0, 1), "4".to_string()), ((2, 3), "4".to_string())];
+        assert_eq!(worksheet.render(&results), "1 2\n3 4\n+ max\n- -\n4 4");
+        let results = vec![((0, 1), "-10".to_string()), ((2, 3), "100".to_string())];
+        assert_eq!(worksheet.render(&results), "1 2\n3 4\n+ max\n- -\n-10 100");
+    }
 }
@@ -0,0 +1,172 @@
+use clap::ValueEnum;
+
+use crate::{Task, WorksheetError, parse_number, parse_operator};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Reading {
+    /// every line of a problem is one operand
+    Rows,
+    /// every column of a problem is one operand, its digits read top to bottom
+    Columns,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Direction {
+    #[value(name = "ltr")]
+    LeftToRight,
+    #[value(name = "rtl")]
+    RightToLeft,
+}
+
+/// A worksheet padded to a rectangle, the last line holds the operators.
+///
+/// Problems are the blocks of columns between columns whose operands only
+/// contain spaces. An operator starts within its block but may be wider than
+/// the operands and reach into the columns to the right of it.
+#[derive(Debug)]
+pub struct Worksheet {
+    rows: Vec<Vec<char>>,
+    // start..end columns of every problem, left to right
+    blocks: Vec<(usize, usize)>,
+}
+
+impl Worksheet {
+    pub fn from_string(s: &str) -> Worksheet {
+        let mut rows: Vec<Vec<char>> = s.lines().map(|line| line.chars().collect()).collect();
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        for row in &mut rows {
+            row.resize(width, ' ');
+        }
+
+        // an operator row alone still forms problems, so they can report missing operands
+        let operand_rows = match rows.split_last() {
+            Some((_, operand_rows)) if !operand_rows.is_empty() => operand_rows,
+            _ => &rows[..],
+        };
+        let mut blocks = Vec::new();
+        let mut start = None;
+        for column in 0..=width {
+            let separator = column == width || operand_rows.iter().all(|row| row[column] == ' ');
+            match (separator, start) {
+                (false, None) => start = Some(column),
+                (true, Some(s)) => {
+                    blocks.push((s, column));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        Worksheet { rows, blocks }
+    }
+
+    pub fn blocks(&self) -> &[(usize, usize)] {
+        &self.blocks
+    }
+
+    /// Operands and operator of every problem, in reading direction.
+    pub fn read(
+        &self,
+        reading: Reading,
+        direction: Direction,
+    ) -> Result<Vec<Task>, WorksheetError> {
+        let Some((operator_row, number_rows)) = self.rows.split_last() else {
+            return Ok(Vec::new());
+        };
+        let mut blocks = self.blocks.clone();
+        if direction == Direction::RightToLeft {
+            blocks.reverse();
+        }
+        let mut tasks = Vec::with_capacity(blocks.len());
+        for (start, end) in blocks {
+            let Some(offset) = operator_row[start..end].iter().position(|&c| c != ' ') else {
+                return Err(WorksheetError::MissingOperator { column: start });
+            };
+            // the token runs up to a space or the next problem
+            let next_start = self
+                .blocks
+                .iter()
+                .map(|&(s, _)| s)
+                .find(|&s| s > start)
+                .unwrap_or(operator_row.len());
+            let token: String = operator_row[start + offset..next_start]
+                .iter()
+                .take_while(|&&c| c != ' ')
+                .collect();
+            let operator = parse_operator(&token, start + offset)?;
+
+            let mut numbers = Vec::new();
+            match reading {
+                Reading::Rows => {
+                    for row in number_rows {
+                        let chars = &row[start..end];
+                        let Some(offset) = chars.iter().position(|&c| c != ' ') else {
+                            continue;
+                        };
+                        // the direction only changes the order of the problems
+                        let token: String = chars.iter().collect();
+                        numbers.push(parse_number(token.trim(), start + offset)?);
+                    }
+                }
+                Reading::Columns => {
+                    let columns: Vec<usize> = match direction {
+                        Direction::LeftToRight => (start..end).collect(),
+                        Direction::RightToLeft => (start..end).rev().collect(),
+                    };
+                    for column in columns {
+                        let token: String = number_rows
+                            .iter()
+                            .map(|row| row[column])
+                            .filter(|&c| c != ' ')
+                            .collect();
+                        if !token.is_empty() {
+                            numbers.push(parse_number(&token, column)?);
+                        }
+                    }
+                }
+            }
+            tasks.push(Task {
+                numbers,
+                operator,
+                columns: (start, end),
+            });
+        }
+        Ok(tasks)
+    }
+
+    /// The worksheet with a rule and a row of results under the problems.
+    ///
+    /// The worksheet itself is copied unchanged. A result ends under the last
+    /// column of its problem, one too wide for that reaches left into the gap
+    /// before it and is pushed right, towards the next gap or the margin, when
+    /// it would run into the result before it.
+    pub fn render(&self, results: &[((usize, usize), String)]) -> String {
+        let mut lines: Vec<String> = self.rows.iter().map(|row| row.iter().collect()).collect();
+        let mut rule = String::new();
+        let mut result_row = String::new();
+        for &(start, end) in &self.blocks {
+            let result = results
+                .iter()
+                .find(|(columns, _)| *columns == (start, end))
+                .map_or("", |(_, result)| result.as_str());
+            rule.extend(std::iter::repeat_n(' ', start - rule.len()));
+            rule.push_str(&"-".repeat(end - start));
+            let width = result.chars().count();
+            let after_previous = if result_row.is_empty() {
+                0
+            } else {
+                result_row.chars().count() + 1
+            };
+            let column = end.saturating_sub(width).max(after_previous);
+            let used = result_row.chars().count();
+            result_row.extend(std::iter::repeat_n(' ', column - used));
+            result_row.push_str(result);
+        }
+        lines.push(rule);
+        lines.push(result_row);
+        lines
+            .iter()
+            .map(|line| line.trim_end())
+            .collect::<Vec<&str>>()
+            .join("\n")
+    }
+}
@@ -0,0 +1,346 @@
+use std::collections::VecDeque;
+
+use crate::{Cell, Count, Edge, OVERFLOW, TachyonManifold};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    fn delta(self) -> (isize, isize) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        }
+    }
+
+    fn from_delta(delta: (isize, isize)) -> Direction {
+        match delta {
+            (0, -1) => Direction::Up,
+            (0, 1) => Direction::Down,
+            (-1, 0) => Direction::Left,
+            _ => Direction::Right,
+        }
+    }
+
+    fn is_vertical(self) -> bool {
+        matches!(self, Direction::Up | Direction::Down)
+    }
+}
+
+// a beam that has been processed by the cell it is in and moves on in `direction`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Beam {
+    x: usize,
+    y: usize,
+    direction: Direction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Next {
+    Beam(Beam),
+    // left the manifold through the top or bottom
+    Exit,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Activity<C> {
+    /// elements other than `S` and `.` that a beam passed through
+    pub activated: usize,
+    /// distinct routes from the sources out of the top or bottom of the manifold
+    pub routes: C,
+}
+
+struct Beams<'a> {
+    manifold: &'a TachyonManifold,
+    edge: Edge,
+    max_y: usize,
+}
+
+impl Beams<'_> {
+    fn id(&self, beam: Beam) -> usize {
+        (beam.y * self.manifold.max_x + beam.x) * 4 + beam.direction as usize
+    }
+
+    fn beam(&self, id: usize) -> Beam {
+        let cell = id / 4;
+        Beam {
+            x: cell % self.manifold.max_x,
+            y: cell / self.manifold.max_x,
+            direction: Direction::ALL[id % 4],
+        }
+    }
+
+    // beams leaving the cell (x, y) that was entered moving in `direction`
+    fn enter(&self, x: usize, y: usize, direction: Direction) -> Vec<Next> {
+        let width = self.manifold.max_x;
+        let beam = |x, direction| Next::Beam(Beam { x, y, direction });
+        let sides = || {
+            [true, false]
+                .into_iter()
+                .filter_map(|left| self.edge.land(x, left, width))
+                .map(|x| beam(x, direction))
+        };
+        let (dx, dy) = direction.delta();
+        match self.manifold.field[y][x] {
+            Cell::Empty | Cell::Start => vec![beam(x, direction)],
+            Cell::Splitter if direction.is_vertical() => sides().collect(),
+            Cell::ThreeWay if direction.is_vertical() => {
+                sides().chain([beam(x, direction)]).collect()
+            }
+            Cell::Splitter | Cell::ThreeWay => vec![beam(x, direction)],
+            Cell::Slash => vec![beam(x, Direction::from_delta((-dy, -dx)))],
+            Cell::Backslash => vec![beam(x, Direction::from_delta((dy, dx)))],
+            Cell::Pipe if direction.is_vertical() => vec![beam(x, direction)],
+            Cell::Pipe | Cell::Absorber => Vec::new(),
+        }
+    }
+
+    // where the beam goes next and the cell it enters on the way, if any
+    fn successors(&self, beam: Beam) -> (Vec<Next>, Option<(usize, usize)>) {
+        let (dx, dy) = beam.direction.delta();
+        let y = beam.y as isize + dy;
+        if y < 0 || y as usize == self.max_y {
+            return (vec![Next::Exit], None);
+        }
+        let x = beam.x as isize + dx;
+        let width = self.manifold.max_x as isize;
+        let x = if (0..width).contains(&x) {
+            x as usize
+        } else {
+            match self.edge {
+                Edge::Absorb => return (Vec::new(), None),
+                Edge::Wrap => x.rem_euclid(width) as usize,
+                Edge::Reflect => {
+                    let direction = Direction::from_delta((-dx, -dy));
+                    return (vec![Next::Beam(Beam { direction, ..beam })], None);
+                }
+            }
+        };
+        let y = y as usize;
+        (self.enter(x, y, beam.direction), Some((x, y)))
+    }
+}
+
+struct Exploration {
+    sources: Vec<usize>,
+    // where every beam reachable from a source goes next
+    successors: Vec<Option<Vec<Next>>>,
+    activated: Vec<bool>,
+}
+
+fn explore(beams: &Beams) -> Exploration {
+    let manifold = beams.manifold;
+    let states = manifold.field.len() * manifold.max_x * 4;
+    let mut successors: Vec<Option<Vec<Next>>> = vec![None; states];
+    let mut activated = vec![false; manifold.field.len() * manifold.max_x];
+    let sources: Vec<usize> = manifold
+        .starts
+        .iter()
+        .map(|&(x, y)| {
+            beams.id(Beam {
+                x,
+                y,
+                direction: Direction::Down,
+            })
+        })
+        .collect();
+    let mut queue: VecDeque<usize> = sources.iter().copied().collect();
+    while let Some(id) = queue.pop_front() {
+        if successors[id].is_some() {
+            continue;
+        }
+        let (next, entered) = beams.successors(beams.beam(id));
+        if let Some((x, y)) = entered
+            && !matches!(manifold.field[y][x], Cell::Empty | Cell::Start)
+        {
+            activated[y * manifold.max_x + x] = true;
+        }
+        for &n in &next {
+            if let Next::Beam(beam) = n {
+                queue.push_back(beams.id(beam));
+            }
+        }
+        successors[id] = Some(next);
+    }
+    Exploration {
+        sources,
+        successors,
+        activated,
+    }
+}
+
+/// Cells beams move through, split by direction, and the elements they
+/// activated, all indexed `[y][x]`.
+pub struct Trails {
+    pub vertical: Vec<Vec<bool>>,
+    pub horizontal: Vec<Vec<bool>>,
+    pub activated: Vec<Vec<bool>>,
+}
+
+pub fn trails(manifold: &TachyonManifold, edge: Edge) -> Trails {
+    let beams = Beams {
+        manifold,
+        edge,
+        max_y: manifold.field.len(),
+    };
+    let exploration = explore(&beams);
+    let empty = vec![vec![false; manifold.max_x]; manifold.field.len()];
+    let (mut vertical, mut horizontal) = (empty.clone(), empty);
+    for (id, next) in exploration.successors.iter().enumerate() {
+        if next.is_some() {
+            let beam = beams.beam(id);
+            if beam.direction.is_vertical() {
+                vertical[beam.y][beam.x] = true;
+            } else {
+                horizontal[beam.y][beam.x] = true;
+            }
+        }
+    }
+    Trails {
+        vertical,
+        horizontal,
+        activated: exploration
+            .activated
+            .chunks(manifold.max_x)
+            .map(|row| row.to_vec())
+            .collect(),
+    }
+}
+
+/// Follows beams that can change direction through every kind of cell.
+///
+/// Sources send a beam down. Mirrors `/` and `\` turn beams, `^` splits
+/// vertical beams to the columns next to it, where they carry on without
+/// entering the cell they land in, and `v` also lets them through. `|` only
+/// lets vertical beams through and `#` stops every beam. Horizontal beams pass
+/// splitters. `edge` handles the left and right wall, beams leaving through
+/// the top or bottom end a route.
+pub fn simulate<C: Count>(manifold: &TachyonManifold, edge: Edge) -> Result<Activity<C>, String> {
+    let beams = Beams {
+        manifold,
+        edge,
+        max_y: manifold.field.len(),
+    };
+    let states = manifold.field.len() * manifold.max_x * 4;
+    let Exploration {
+        sources,
+        successors,
+        activated,
+    } = explore(&beams);
+
+    // beams that still have a way out of the manifold
+    let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); states];
+    let mut escapes = vec![false; states];
+    let mut queue = VecDeque::new();
+    for (id, next) in successors.iter().enumerate() {
+        for n in next.iter().flatten() {
+            match n {
+                Next::Beam(beam) => predecessors[beams.id(*beam)].push(id),
+                Next::Exit if !escapes[id] => {
+                    escapes[id] = true;
+                    queue.push_back(id);
+                }
+                Next::Exit => {}
+            }
+        }
+    }
+    while let Some(id) = queue.pop_front() {
+        for &p in &predecessors[id] {
+            if !escapes[p] {
+                escapes[p] = true;
+                queue.push_back(p);
+            }
+        }
+    }
+
+    // count routes in reverse topological order, a cycle with a way out
+    // would allow infinitely many
+    let mut pending = vec![0usize; states];
+    for (id, next) in successors.iter().enumerate() {
+        if escapes[id] {
+            for n in next.iter().flatten() {
+                if let Next::Beam(beam) = n
+                    && escapes[beams.id(*beam)]
+                {
+                    pending[id] += 1;
+                }
+            }
+        }
+    }
+    let mut routes: Vec<Option<C>> = vec![None; states];
+    let mut queue: VecDeque<usize> = (0..states)
+        .filter(|&id| escapes[id] && pending[id] == 0)
+        .collect();
+    while let Some(id) = queue.pop_front() {
+        let mut count = C::zero();
+        for n in successors[id].iter().flatten() {
+            let add = match n {
+                Next::Exit => C::one(),
+                Next::Beam(beam) => match &routes[beams.id(*beam)] {
+                    Some(c) => c.clone(),
+                    None => continue,
+                },
+            };
+            count = count.checked_add(&add).ok_or(OVERFLOW)?;
+        }
+        routes[id] = Some(count);
+        for &p in &predecessors[id] {
+            if escapes[p] {
+                pending[p] -= 1;
+                if pending[p] == 0 {
+                    queue.push_back(p);
+                }
+            }
+        }
+    }
+    if let Some(mut id) = (0..states).find(|&id| escapes[id] && routes[id].is_none()) {
+        // walk along unresolved beams until one repeats, that one is on the loop
+        let mut seen = vec![false; states];
+        while !seen[id] {
+            seen[id] = true;
+            id = successors[id]
+                .iter()
+                .flatten()
+                .find_map(|n| match n {
+                    Next::Beam(beam)
+                        if routes[beams.id(*beam)].is_none() && escapes[beams.id(*beam)] =>
+                    {
+                        Some(beams.id(*beam))
+                    }
+                    _ => None,
+                })
+                .unwrap();
+        }
+        let beam = beams.beam(id);
+        return Err(format!(
+            "Beams loop through {},{} and still get out, the number of routes is unbounded",
+            beam.x + 1,
+            beam.y + 1
+        ));
+    }
+
+    let mut total = C::zero();
+    for &source in &sources {
+        if let Some(count) = &routes[source] {
+            total = total.checked_add(count).ok_or(OVERFLOW)?;
+        }
+    }
+    Ok(Activity {
+        activated: activated.iter().filter(|&&a| a).count(),
+        routes: total,
+    })
+}
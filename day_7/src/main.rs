@@ -1,264 +1,561 @@
+use clap::{Parser, ValueEnum};
 use mt_logger::*;
-use std::{collections::HashMap, mem};
+use num_bigint::BigUint;
+use utils::input::Input;
 
-#[derive(Debug, Clone, Copy)]
-struct Start {
-    position: (usize, usize),
-    child: Option<usize>,
+mod beams;
+mod render;
+
+use render::HeatMapFormat;
+
+#[derive(Parser, Debug)]
+struct Args {
+    /// manifold with starts 'S', splitters '^' and 'v', mirrors '/' and '\\',
+    /// pipes '|' and absorbers '#'
+    filename: String,
+    /// what happens to beams split off the left or right edge
+    #[arg(long, value_enum, default_value_t = Edge::Absorb)]
+    edge: Edge,
+    /// count timelines with arbitrary precision instead of checked u128
+    #[arg(long)]
+    bigint: bool,
+    /// how to count the timelines for part 2
+    #[arg(long, value_enum, default_value_t = Algorithm::Sweep)]
+    algorithm: Algorithm,
+    /// print the manifold with the beam trails
+    #[arg(long)]
+    render: bool,
+    /// highlight the trails and the elements hit by a beam in the rendering
+    #[arg(long)]
+    colour: bool,
+    /// write a heat map of the timelines through every cell to this file
+    #[arg(long)]
+    heat_map: Option<String>,
+    #[arg(long, value_enum, default_value_t = HeatMapFormat::Text)]
+    heat_map_format: HeatMapFormat,
+    /// pixels per cell in a ppm heat map
+    #[arg(long, default_value_t = 4)]
+    scale: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum Edge {
+    /// the beam leaves the manifold
+    Absorb,
+    /// the beam comes back in on the opposite side
+    Wrap,
+    /// the beam bounces off the wall into the edge column
+    Reflect,
+}
+
+impl Edge {
+    // column a beam split off `x` ends up in, if it stays in the manifold
+    fn land(self, x: usize, left: bool, width: usize) -> Option<usize> {
+        match (left, self) {
+            (true, _) if x > 0 => Some(x - 1),
+            (false, _) if x + 1 < width => Some(x + 1),
+            (_, Edge::Absorb) => None,
+            (true, Edge::Wrap) | (false, Edge::Reflect) => Some(width - 1),
+            (false, Edge::Wrap) | (true, Edge::Reflect) => Some(0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum Algorithm {
+    /// push the beams down row by row
+    Sweep,
+    /// count the routes to the bottom from every cell, bottom row first
+    Dp,
+    /// follow beams that change direction, used for every manifold with
+    /// more than starts and '^' splitters
+    Beams,
 }
-#[derive(Debug, Clone, Copy)]
-struct Splitter {
-    position: (usize, usize),
-    hit: bool,
-    child_left: Option<usize>,
-    child_right: Option<usize>,
+
+/// Timeline counter, the number of timelines doubles with every splitter.
+trait Count: Clone + std::fmt::Display {
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn is_zero(&self) -> bool;
+    fn log2(&self) -> f64;
+}
+
+impl Count for u128 {
+    fn zero() -> Self {
+        0
+    }
+    fn one() -> Self {
+        1
+    }
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        u128::checked_add(*self, *other)
+    }
+    fn is_zero(&self) -> bool {
+        *self == 0
+    }
+    fn log2(&self) -> f64 {
+        (*self as f64).log2()
+    }
 }
-#[derive(Debug, Clone, Copy)]
-struct Beam {
-    position: (usize, usize),
-    child: Option<usize>,
+
+impl Count for BigUint {
+    fn zero() -> Self {
+        BigUint::ZERO
+    }
+    fn one() -> Self {
+        BigUint::from(1u8)
+    }
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self + other)
+    }
+    fn is_zero(&self) -> bool {
+        *self == BigUint::ZERO
+    }
+    fn log2(&self) -> f64 {
+        // the top 64 bits are plenty for a heat map
+        let shift = self.bits().saturating_sub(64);
+        let top = (self >> shift).iter_u64_digits().next().unwrap_or(0);
+        (top as f64).log2() + shift as f64
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Objects {
-    Start(Start),
-    Splitter(Splitter),
-    Beam(Beam),
-    None,
+const OVERFLOW: &str = "Timeline count overflows u128, try --bigint";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cell {
+    Empty,
+    Start,
+    /// `^`
+    Splitter,
+    /// `v`, splits like `^` but also lets the beam through
+    ThreeWay,
+    /// `/`
+    Slash,
+    /// `\`
+    Backslash,
+    /// `|`
+    Pipe,
+    /// `#`
+    Absorber,
+}
+
+impl Cell {
+    fn from_char(c: char) -> Option<Cell> {
+        Some(match c {
+            '.' => Cell::Empty,
+            'S' => Cell::Start,
+            '^' => Cell::Splitter,
+            'v' => Cell::ThreeWay,
+            '/' => Cell::Slash,
+            '\\' => Cell::Backslash,
+            '|' => Cell::Pipe,
+            '#' => Cell::Absorber,
+            _ => return None,
+        })
+    }
+
+    fn symbol(self) -> char {
+        match self {
+            Cell::Empty => '.',
+            Cell::Start => 'S',
+            Cell::Splitter => '^',
+            Cell::ThreeWay => 'v',
+            Cell::Slash => '/',
+            Cell::Backslash => '\\',
+            Cell::Pipe => '|',
+            Cell::Absorber => '#',
+        }
+    }
 }
 
 #[derive(Debug)]
 struct TachyonManifold {
-    objects: Vec<Objects>,
-    start: usize,
-    splitters: Vec<usize>,
-    beams: Vec<usize>,
-    max_y: usize,
+    // row major, field[y][x]
+    field: Vec<Vec<Cell>>,
+    starts: Vec<(usize, usize)>,
     max_x: usize,
-    open_beams: Vec<usize>,
-    field: Vec<Vec<Objects>>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Sweep<C> {
+    splitters_hit: usize,
+    timelines: C,
+    // timelines whose beam passes each cell coming from above or starting there, [y][x]
+    arrivals: Vec<Vec<C>>,
 }
 
 impl TachyonManifold {
-    fn from_string(s: &str) -> TachyonManifold {
-        let mut objects: Vec<Objects> = Vec::new();
-        let mut start: usize = 0;
-        let mut splitters: Vec<usize> = Vec::new();
-
-        let max_y = s.lines().count();
-        let max_x = s.lines().next().unwrap().chars().count();
-
-        let mut field: Vec<Vec<Objects>> = vec![vec![Objects::None; max_y]; max_x];
-
-        for (y, line) in s.lines().enumerate() {
-            for (x, c) in line.chars().enumerate() {
-                if c == 'S' {
-                    objects.push(Objects::Start(Start {
-                        position: (x, y),
-                        child: None,
-                    }));
-                    start = objects.len() - 1;
-                    field[x][y] = Objects::Start(Start {
-                        position: (x, y),
-                        child: None,
-                    })
-                } else if c == '^' {
-                    objects.push(Objects::Splitter(Splitter {
-                        position: (x, y),
-                        hit: false,
-                        child_left: None,
-                        child_right: None,
-                    }));
-                    splitters.push(objects.len() - 1);
-                    field[x][y] = Objects::Splitter(Splitter {
-                        position: (x, y),
-                        hit: false,
-                        child_left: None,
-                        child_right: None,
-                    })
+    fn from_grid(grid: &[Vec<char>]) -> Result<TachyonManifold, String> {
+        let mut starts = Vec::new();
+        let mut field = Vec::with_capacity(grid.len());
+        for (y, line) in grid.iter().enumerate() {
+            let mut row = Vec::with_capacity(line.len());
+            for (x, &c) in line.iter().enumerate() {
+                let cell = Cell::from_char(c)
+                    .ok_or_else(|| format!("Unknown element '{}' at {},{}", c, x + 1, y + 1))?;
+                if cell == Cell::Start {
+                    starts.push((x, y));
                 }
+                row.push(cell);
             }
+            field.push(row);
         }
-        TachyonManifold {
-            objects,
-            start,
-            splitters,
-            beams: Vec::new(),
-            max_y,
-            max_x,
-            open_beams: Vec::new(),
-            field,
+        if starts.is_empty() {
+            return Err("Manifold has no start 'S'".to_string());
         }
+        Ok(TachyonManifold {
+            max_x: field[0].len(),
+            field,
+            starts,
+        })
     }
 
-    fn get_spitters(&mut self) -> Vec<Splitter> {
-        self.splitters
-            .iter_mut()
-            .map(|splitter_index| {
-                let Objects::Splitter(splitter) = self.objects[*splitter_index] else {
-                    panic!();
-                };
-                splitter
-            })
-            .collect::<Vec<Splitter>>()
-    }
-
-    fn get_beams(&self) -> Vec<&Beam> {
-        self.beams
+    /// Only starts and `^` splitters, beams never leave their column
+    /// downwards.
+    fn is_classic(&self) -> bool {
+        self.field
             .iter()
-            .map(|beam_index| {
-                let Objects::Beam(beam) = &self.objects[*beam_index] else {
-                    panic!();
-                };
-                beam
-            })
-            .collect::<Vec<&Beam>>()
+            .flatten()
+            .all(|cell| matches!(cell, Cell::Empty | Cell::Start | Cell::Splitter))
     }
 
-    fn simulate(&mut self) {
-        let Objects::Start(start) = &self.objects[self.start] else {
-            mt_log!(
-                Level::Error,
-                "Start_Index: {}, Objects: {:?}",
-                self.start,
-                self.objects
-            );
-            mt_flush!().unwrap();
-            panic!();
-        };
-        let beam: Beam = Beam {
-            position: (start.position.0, start.position.1 + 1),
-            child: None,
-        };
-        self.objects.push(Objects::Beam(beam));
-        self.beams.push(self.objects.len() - 1);
-        self.open_beams.push(self.objects.len() - 1);
-        while let Some(beam_index) = self.open_beams.pop() {
-            let new_position = {
-                let Objects::Beam(beam) = self.objects.get_mut(beam_index).unwrap() else {
-                    panic!();
-                };
-
-                (beam.position.0, beam.position.1 + 1)
+    /// Moves the beams down one row at a time, keeping the number of
+    /// timelines that put a beam into each column.
+    ///
+    /// A splitter sends the beams that hit it to the columns left and right of
+    /// it, `edge` decides about the beams split off the sides. Only valid for
+    /// classic manifolds.
+    fn sweep<C: Count>(&self, edge: Edge) -> Result<Sweep<C>, String> {
+        let mut beams = vec![C::zero(); self.max_x];
+        let mut splitters_hit = 0;
+        let mut arrivals = Vec::with_capacity(self.field.len());
+        for row in &self.field {
+            let mut arriving = beams.clone();
+            for (x, &cell) in row.iter().enumerate() {
+                if cell == Cell::Start {
+                    arriving[x] = arriving[x].checked_add(&C::one()).ok_or(OVERFLOW)?;
+                }
+            }
+            arrivals.push(arriving);
+            let mut next = vec![C::zero(); self.max_x];
+            let mut add = |x: Option<usize>, count: &C| -> Result<(), String> {
+                if let Some(x) = x {
+                    next[x] = next[x].checked_add(count).ok_or(OVERFLOW)?;
+                }
+                Ok(())
             };
-            if new_position.1 == self.max_y {
-                mt_log!(Level::Debug, "Beam hit bottom");
-                continue;
+            for (x, count) in beams.iter().enumerate() {
+                if count.is_zero() {
+                    continue;
+                }
+                if row[x] == Cell::Splitter {
+                    splitters_hit += 1;
+                    add(edge.land(x, true, self.max_x), count)?;
+                    add(edge.land(x, false, self.max_x), count)?;
+                } else {
+                    add(Some(x), count)?;
+                }
             }
-            let splitter_index = self
-                .objects
-                .iter()
-                .position(|o| matches!(o, Objects::Splitter(s) if s.position == new_position));
-
-            if let Some(idx) = splitter_index {
-                let (x, y) = {
-                    let splitter = match &mut self.objects[idx] {
-                        Objects::Splitter(s) => s,
-                        _ => unreachable!(),
-                    };
+            for (x, &cell) in row.iter().enumerate() {
+                if cell == Cell::Start {
+                    add(Some(x), &C::one())?;
+                }
+            }
+            beams = next;
+        }
+        let mut timelines = C::zero();
+        for count in &beams {
+            timelines = timelines.checked_add(count).ok_or(OVERFLOW)?;
+        }
+        Ok(Sweep {
+            splitters_hit,
+            timelines,
+            arrivals,
+        })
+    }
 
-                    mt_log!(Level::Debug, "Splitter got hit at {:?}", new_position);
-                    splitter.hit = true;
-                    (splitter.position.0, splitter.position.1)
-                };
-                self.create_beam((x - 1, y), beam_index);
-                self.create_beam((x + 1, y), beam_index);
-            } else {
-                self.create_beam(new_position, beam_index);
+    /// Number of timelines a beam entering each cell from above ends up in.
+    ///
+    /// Filled bottom row first, so a cell only looks at the row below it.
+    fn routes<C: Count>(&self, edge: Edge) -> Result<Vec<Vec<C>>, String> {
+        let below_bottom = vec![C::one(); self.max_x];
+        let mut routes: Vec<Vec<C>> = Vec::with_capacity(self.field.len());
+        for row in self.field.iter().rev() {
+            let below = routes.last().unwrap_or(&below_bottom);
+            let mut current = Vec::with_capacity(self.max_x);
+            for (x, &cell) in row.iter().enumerate() {
+                current.push(if cell == Cell::Splitter {
+                    let side = |left| {
+                        edge.land(x, left, self.max_x)
+                            .map_or(C::zero(), |x| below[x].clone())
+                    };
+                    side(true).checked_add(&side(false)).ok_or(OVERFLOW)?
+                } else {
+                    below[x].clone()
+                });
             }
+            routes.push(current);
         }
+        routes.reverse();
+        Ok(routes)
+    }
 
-        let count = self.get_spitters().iter().filter(|s| s.hit).count();
-        mt_log!(Level::Info, "Result Part 1: {}", count);
+    /// Elements hit (splitters for classic manifolds) and timelines.
+    fn timelines<C: Count>(&self, edge: Edge, algorithm: Algorithm) -> Result<(usize, C), String> {
+        if algorithm == Algorithm::Beams || !self.is_classic() {
+            let activity = beams::simulate::<C>(self, edge)?;
+            return Ok((activity.activated, activity.routes));
+        }
+        let sweep = self.sweep::<C>(edge)?;
+        let timelines = match algorithm {
+            Algorithm::Dp => {
+                let routes = self.routes::<C>(edge)?;
+                let mut timelines = C::zero();
+                for &(x, y) in &self.starts {
+                    timelines = timelines.checked_add(&routes[y][x]).ok_or(OVERFLOW)?;
+                }
+                timelines
+            }
+            _ => sweep.timelines,
+        };
+        Ok((sweep.splitters_hit, timelines))
     }
+}
+
+fn heat_map<C: Count>(
+    tachyon_manifold: &TachyonManifold,
+    trails: &beams::Trails,
+    edge: Edge,
+) -> Result<Vec<Vec<Option<f64>>>, String> {
+    let sweep = tachyon_manifold.sweep::<C>(edge)?;
+    let routes = tachyon_manifold.routes::<C>(edge)?;
+    Ok(render::heat(trails, &sweep.arrivals, &routes))
+}
+
+fn main() {
+    mt_new!(None, Level::Info, OutputStream::StdOut, true);
+    let args = Args::parse();
+    let filepath = &args.filename;
+    let filecontent = Input::read(filepath).expect("Could not read file");
+    let tachyon_manifold = match filecontent
+        .grid()
+        .and_then(|grid| TachyonManifold::from_grid(&grid))
+    {
+        Ok(tachyon_manifold) => tachyon_manifold,
+        Err(e) => {
+            mt_log!(Level::Error, "{}: {}", filepath, e);
+            mt_flush!().unwrap();
+            std::process::exit(1);
+        }
+    };
+    mt_log!(Level::Debug, "{:?}", tachyon_manifold);
 
-    fn count_routes(
-        &mut self,
-        position: (usize, usize),
-        memo: &mut HashMap<(usize, usize), usize>,
-    ) -> usize {
-        let (x, y) = position;
-        if let Some(v) = memo.get(&position) {
-            return *v;
+    let result = if args.bigint {
+        tachyon_manifold
+            .timelines::<BigUint>(args.edge, args.algorithm)
+            .map(|(hit, timelines)| (hit, timelines.to_string()))
+    } else {
+        tachyon_manifold
+            .timelines::<u128>(args.edge, args.algorithm)
+            .map(|(hit, timelines)| (hit, timelines.to_string()))
+    };
+    let mut failed = false;
+    match result {
+        Ok((splitters_hit, timelines)) => {
+            mt_log!(Level::Info, "Result Part 1: {}", splitters_hit);
+            mt_log!(Level::Info, "Result Part 2: {}", timelines);
         }
-        mt_log!(Level::Debug, "{}", memo.capacity());
-        if y == self.max_y {
-            return 1;
+        Err(e) => {
+            mt_log!(Level::Error, "{}", e);
+            failed = true;
         }
+    }
 
-        match self.field[x][y] {
-            Objects::Splitter(_) => {
-                let (mut left, mut right) = (0, 0);
-                if x > 0 {
-                    left = self.count_routes((x - 1, y), memo);
+    if args.render || args.heat_map.is_some() {
+        let trails = beams::trails(&tachyon_manifold, args.edge);
+        if args.render {
+            mt_log!(
+                Level::Info,
+                "\n{}",
+                render::render_trails(&tachyon_manifold, &trails, args.colour)
+            );
+        }
+        if let Some(path) = &args.heat_map {
+            let heat = if !tachyon_manifold.is_classic() {
+                Err("The heat map needs a manifold of only starts and '^' splitters".to_string())
+            } else if args.bigint {
+                heat_map::<BigUint>(&tachyon_manifold, &trails, args.edge)
+            } else {
+                heat_map::<u128>(&tachyon_manifold, &trails, args.edge)
+            };
+            let written = heat.and_then(|heat| {
+                render::write_heat_map(
+                    path,
+                    &tachyon_manifold,
+                    &heat,
+                    args.heat_map_format,
+                    args.scale.max(1),
+                )
+                .map_err(|e| format!("Could not write heat map to {}: {}", path, e))
+            });
+            match written {
+                Ok(()) => mt_log!(Level::Info, "Wrote heat map to {}", path),
+                Err(e) => {
+                    mt_log!(Level::Error, "{}", e);
+                    failed = true;
                 }
-                if x < self.max_x {
-                    right = self.count_routes((x + 1, y), memo);
+            }
+        }
+    }
+    mt_flush!().unwrap();
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = ".......S.......
+...............
+.......^.......
+...............
+......^.^......
+...............
+.....^.^.^.....
+...............
+....^.^...^....
+...............
+...^.^...^.^...
+...............
+..^...^.....^..
+...............
+.^.^.^.^.^...^.
+...............";
+
+    #[test]
+    fn sweep_solves_example() {
+        let grid = Input::from_string(EXAMPLE).grid().unwrap();
+        let tachyon_manifold = TachyonManifold::from_grid(&grid).unwrap();
+        let sweep = tachyon_manifold.sweep::<u128>(Edge::Absorb).unwrap();
+        assert_eq!((sweep.splitters_hit, sweep.timelines), (21, 40));
+        assert_eq!(sweep.arrivals[15].iter().sum::<u128>(), 40);
+    }
+
+    #[test]
+    fn sweep_and_dp_agree_for_every_edge() {
+        // splitters on the edges and next to each other
+        let mut state = 12345u64;
+        for _ in 0..200 {
+            let mut next = || {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (state >> 33) as usize
+            };
+            let (width, height) = (1 + next() % 8, 2 + next() % 12);
+            let mut grid = vec![vec!['.'; width]; height];
+            grid[0][next() % width] = 'S';
+            grid[next() % 2][next() % width] = 'S';
+            for row in grid.iter_mut().skip(1) {
+                for c in row.iter_mut() {
+                    if next() % 3 == 0 {
+                        *c = '^';
+                    }
                 }
-                memo.insert(position, left + right);
-                left + right
             }
-            _ => {
-                let value = self.count_routes((x, y + 1), memo);
-                memo.insert(position, value);
-                value
+            let tachyon_manifold = TachyonManifold::from_grid(&grid).unwrap();
+            for edge in [Edge::Absorb, Edge::Wrap, Edge::Reflect] {
+                let sweep = tachyon_manifold.timelines::<u128>(edge, Algorithm::Sweep);
+                let dp = tachyon_manifold.timelines::<u128>(edge, Algorithm::Dp);
+                let beams = tachyon_manifold.timelines::<u128>(edge, Algorithm::Beams);
+                assert_eq!(sweep, dp, "{:?} {:?}", edge, grid);
+                assert_eq!(sweep, beams, "{:?} {:?}", edge, grid);
             }
         }
     }
-    fn create_beam(&mut self, new_position: (usize, usize), parent_index: usize) {
-        if self
-            .get_beams()
-            .iter()
-            .any(|beam| beam.position == new_position)
-        {
-            mt_log!(Level::Debug, "Beam @ {:?} already exsits", new_position);
-            return;
-        }
-        let new_beam = Objects::Beam(Beam {
-            position: new_position,
-            child: None,
-        });
-        mt_log!(Level::Debug, "Created new Beam at {:?}", new_position);
-        self.objects.push(new_beam);
-
-        let new_child_index = self.objects.len() - 1;
-        self.beams.push(new_child_index);
-        self.open_beams.push(new_child_index);
-        let Objects::Beam(beam) = self.objects.get_mut(parent_index).unwrap() else {
-            panic!();
-        };
-        beam.child = Some(new_child_index);
+
+    #[test]
+    fn checked_counts_overflow_and_bigint_does_not() {
+        // 130 splitter rows double the timelines every time with wrapping edges
+        let mut grid = vec![vec!['S', '.']];
+        grid.extend((0..130).map(|_| vec!['^', '^']));
+        let tachyon_manifold = TachyonManifold::from_grid(&grid).unwrap();
+        assert!(tachyon_manifold.sweep::<u128>(Edge::Wrap).is_err());
+        assert!(tachyon_manifold.routes::<u128>(Edge::Wrap).is_err());
+        let (_, timelines) = tachyon_manifold
+            .timelines::<BigUint>(Edge::Wrap, Algorithm::Dp)
+            .unwrap();
+        assert_eq!(timelines, BigUint::from(1u8) << 130);
     }
-}
 
-fn main() {
-    mt_new!(None, Level::Info, OutputStream::StdOut, true);
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() < 2 {
-        mt_log!(Level::Error, "Usage {} <inputfile.txt>", args[0]);
-        mt_flush!().unwrap();
-        std::process::exit(1);
+    fn solve(s: &str, edge: Edge) -> Result<(usize, u128), String> {
+        let grid = Input::from_string(s).grid().unwrap();
+        TachyonManifold::from_grid(&grid)?.timelines::<u128>(edge, Algorithm::Sweep)
+    }
+
+    #[test]
+    fn beams_follow_mirrors_pipes_and_absorbers() {
+        // down, '\' turns right, '/' turns up and out of the top
+        assert_eq!(solve("S..\n\\./\n...", Edge::Absorb), Ok((2, 1)));
+        // 'v' splits three ways and one of them ends in '#'
+        assert_eq!(solve(".S.\n.v.\n#..", Edge::Absorb), Ok((2, 2)));
+        // split beams land next to the splitter without entering that cell
+        assert_eq!(solve(".S.\n/v|\n...", Edge::Absorb), Ok((1, 3)));
+        // pipes let vertical beams through and stop horizontal ones
+        assert_eq!(solve(".S.\n...\n.|.", Edge::Absorb), Ok((1, 1)));
+        assert_eq!(solve("S..\n\\|.\n...", Edge::Absorb), Ok((2, 0)));
+        // two sources
+        assert_eq!(solve("S.S\n...\n.^.", Edge::Absorb), Ok((0, 2)));
+        assert_eq!(solve("S.S\n^.^", Edge::Wrap), Ok((2, 4)));
+        assert!(solve("S.x", Edge::Absorb).is_err());
+    }
+
+    #[test]
+    fn beams_detect_unbounded_loops() {
+        // the '^' feeds the loop of mirrors, the 'v' on it keeps letting beams out
+        let looping = r"....S.
+./.\..
+.v..^.
+.\./..";
+        let error = solve(looping, Edge::Absorb).unwrap_err();
+        assert!(error.contains("unbounded"), "{}", error);
+        // without the 'v' the loop has no way out and adds no routes
+        let closed = r"....S.
+./.\..
+....^.
+.\./..";
+        assert_eq!(solve(closed, Edge::Absorb), Ok((5, 1)));
+    }
+
+    #[test]
+    fn renders_trails_and_heat() {
+        let grid = Input::from_string("..S..\n.....\n..^..\n.^...\n.....")
+            .grid()
+            .unwrap();
+        let tachyon_manifold = TachyonManifold::from_grid(&grid).unwrap();
+        let trails = beams::trails(&tachyon_manifold, Edge::Absorb);
+        assert_eq!(
+            render::render_trails(&tachyon_manifold, &trails, false),
+            "..S..\n..|..\n.|▲|.\n|▲||.\n|.||."
+        );
+        let heat = heat_map::<u128>(&tachyon_manifold, &trails, Edge::Absorb).unwrap();
+        assert_eq!(heat[4][0], heat[4][2]);
+        assert_eq!(heat[4][1], None);
+
+        // two splitters send beams into the middle column, where timelines converge
+        let grid = Input::from_string("...S...\n...^...\n..^.^..\n.......")
+            .grid()
+            .unwrap();
+        let tachyon_manifold = TachyonManifold::from_grid(&grid).unwrap();
+        let trails = beams::trails(&tachyon_manifold, Edge::Absorb);
+        let heat = heat_map::<u128>(&tachyon_manifold, &trails, Edge::Absorb).unwrap();
+        // 4, 2 and 1 of the 4 timelines, on a log scale
+        assert_eq!(heat[0][3], Some(1.0));
+        assert_eq!(heat[3][3], Some(2.0 / 3.0));
+        assert_eq!(heat[3][1], Some(1.0 / 3.0));
+        assert_eq!(heat[3][1], heat[3][5]);
     }
-    let filepath = &args[1];
-    let filecontent = std::fs::read_to_string(filepath).expect("Could not read file");
-    let mut tachyon_manifold = TachyonManifold::from_string(&filecontent);
-    mt_log!(Level::Debug, "{:?}", tachyon_manifold);
-    tachyon_manifold.simulate();
-
-    // Own Idea was to simulate - allow "double beams" and count beams with y=max_y at the end but that took way too long
-    // Unfortunatly i got this idea when i looked on reddit for ideas - i was tired
-    let start_position = match tachyon_manifold
-        .objects
-        .get(tachyon_manifold.start)
-        .unwrap()
-    {
-        Objects::Start(s) => s.position,
-        _ => panic!(),
-    };
-    mt_log!(
-        Level::Info,
-        "Result Part 2: {}",
-        tachyon_manifold.count_routes(start_position, &mut HashMap::new())
-    );
-    mt_flush!().unwrap();
 }
@@ -0,0 +1,151 @@
+use std::{fs, io};
+
+use clap::ValueEnum;
+
+use crate::{Cell, Count, TachyonManifold, beams::Trails};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum HeatMapFormat {
+    /// one character per cell, darker to brighter
+    Text,
+    Ppm,
+}
+
+const HIT: &str = "\x1b[1;31m";
+const TRAIL: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+// hit splitters stand out without colour as well
+fn hit_symbol(cell: Cell) -> char {
+    match cell {
+        Cell::Splitter => '▲',
+        Cell::ThreeWay => '▼',
+        _ => cell.symbol(),
+    }
+}
+
+/// The manifold with `|` and `-` where beams went (`+` for both) and hit
+/// splitters drawn as `▲` (`▼` for `v`). With `colour` the trails and the
+/// elements the beams activated are highlighted.
+pub fn render_trails(manifold: &TachyonManifold, trails: &Trails, colour: bool) -> String {
+    let mut lines = Vec::with_capacity(manifold.field.len());
+    for (y, row) in manifold.field.iter().enumerate() {
+        let mut line = String::new();
+        for (x, &cell) in row.iter().enumerate() {
+            let (c, highlight) = match (cell, trails.vertical[y][x], trails.horizontal[y][x]) {
+                (Cell::Empty, true, true) => ('+', TRAIL),
+                (Cell::Empty, true, false) => ('|', TRAIL),
+                (Cell::Empty, false, true) => ('-', TRAIL),
+                _ if trails.activated[y][x] => (hit_symbol(cell), HIT),
+                _ => (cell.symbol(), ""),
+            };
+            if colour && !highlight.is_empty() {
+                line.push_str(highlight);
+                line.push(c);
+                line.push_str(RESET);
+            } else {
+                line.push(c);
+            }
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+/// Timelines through every cell a beam reaches, on a log scale up to 1 for
+/// the most, `None` for the cells no beam reaches.
+///
+/// The timelines through a cell are those arriving at it, `arrivals`, times
+/// the ways on from there, `routes`, so cells where paths converge stand out.
+pub fn heat<C: Count>(
+    trails: &Trails,
+    arrivals: &[Vec<C>],
+    routes: &[Vec<C>],
+) -> Vec<Vec<Option<f64>>> {
+    let logs: Vec<Vec<Option<f64>>> = arrivals
+        .iter()
+        .zip(routes)
+        .enumerate()
+        .map(|(y, (arrivals_row, routes_row))| {
+            arrivals_row
+                .iter()
+                .zip(routes_row)
+                .enumerate()
+                .map(|(x, (arriving, onwards))| {
+                    (trails.vertical[y][x] && !arriving.is_zero() && !onwards.is_zero())
+                        .then(|| arriving.log2() + onwards.log2() + 1.0)
+                })
+                .collect()
+        })
+        .collect();
+    let max = logs
+        .iter()
+        .flatten()
+        .flatten()
+        .fold(1.0, |a: f64, &b| a.max(b));
+    logs.iter()
+        .map(|row| row.iter().map(|log| log.map(|log| log / max)).collect())
+        .collect()
+}
+
+const RAMP: [char; 9] = ['.', ':', '-', '=', '+', '*', '#', '%', '@'];
+
+fn heat_char(heat: Option<f64>, cell: Cell) -> char {
+    match (heat, cell) {
+        (Some(h), Cell::Empty | Cell::Start) => {
+            RAMP[(h * (RAMP.len() - 1) as f64).round() as usize]
+        }
+        (None, Cell::Empty) => ' ',
+        _ => cell.symbol(),
+    }
+}
+
+// black through red and yellow to white
+fn heat_colour(heat: Option<f64>) -> [u8; 3] {
+    let Some(h) = heat else {
+        return [0, 0, 0];
+    };
+    let channel = |from: f64| ((h * 3.0 - from).clamp(0.0, 1.0) * 255.0) as u8;
+    [channel(0.0).max(40), channel(1.0), channel(2.0)]
+}
+
+pub fn write_heat_map(
+    path: &str,
+    manifold: &TachyonManifold,
+    heat: &[Vec<Option<f64>>],
+    format: HeatMapFormat,
+    scale: usize,
+) -> io::Result<()> {
+    match format {
+        HeatMapFormat::Text => {
+            let mut s = String::new();
+            for (row, heat_row) in manifold.field.iter().zip(heat) {
+                s.extend(
+                    row.iter()
+                        .zip(heat_row)
+                        .map(|(&cell, &h)| heat_char(h, cell)),
+                );
+                s.push('\n');
+            }
+            fs::write(path, s)
+        }
+        HeatMapFormat::Ppm => {
+            let (width, height) = (manifold.max_x * scale, manifold.field.len() * scale);
+            let mut data = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+            for (row, heat_row) in manifold.field.iter().zip(heat) {
+                for _ in 0..scale {
+                    for (&cell, &h) in row.iter().zip(heat_row) {
+                        let colour = match cell {
+                            Cell::Empty | Cell::Start => heat_colour(h),
+                            _ => [90, 140, 255],
+                        };
+                        for _ in 0..scale {
+                            data.extend_from_slice(&colour);
+                        }
+                    }
+                }
+            }
+            fs::write(path, data)
+        }
+    }
+}
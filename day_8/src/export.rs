@@ -0,0 +1,110 @@
+use std::{fs, io};
+
+use clap::ValueEnum;
+
+use crate::{Connection, JunctionField};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum MstFormat {
+    /// one cable per line in the order they were added, with a running total
+    Csv,
+    /// Graphviz graph with the boxes placed by their x and y
+    Dot,
+    /// Wavefront OBJ with a vertex per box and a line per cable
+    Obj,
+}
+
+pub fn total_length(connections: &[Connection]) -> f64 {
+    connections.iter().map(|c| c.length).sum()
+}
+
+fn csv(field: &JunctionField, connections: &[Connection]) -> String {
+    let mut s = String::from("order,a,b,ax,ay,az,bx,by,bz,length,total\n");
+    let mut total = 0.0;
+    for (i, c) in connections.iter().enumerate() {
+        total += c.length;
+        s.push_str(&format!(
+            "{},{},{},{},{},{},{}\n",
+            i + 1,
+            c.a,
+            c.b,
+            field.format_box(c.a),
+            field.format_box(c.b),
+            c.length,
+            total
+        ));
+    }
+    s
+}
+
+fn dot(field: &JunctionField, connections: &[Connection]) -> String {
+    let mut s = format!(
+        "// minimum spanning tree, total length {}\ngraph mst {{\n",
+        total_length(connections)
+    );
+    for (b, point) in field.positions.iter().enumerate() {
+        let (x, y, _) = point.position;
+        s.push_str(&format!(
+            "  {} [label=\"{}\", pos=\"{},{}!\"];\n",
+            b,
+            field.format_box(b),
+            x,
+            y
+        ));
+    }
+    let last = connections.len().saturating_sub(1);
+    for (i, c) in connections.iter().enumerate() {
+        let highlight = if i == last {
+            ", color=red, penwidth=3"
+        } else {
+            ""
+        };
+        s.push_str(&format!(
+            "  {} -- {} [label=\"{}: {:.2}\"{}];\n",
+            c.a,
+            c.b,
+            i + 1,
+            c.length,
+            highlight
+        ));
+    }
+    s.push_str("}\n");
+    s
+}
+
+fn obj(field: &JunctionField, connections: &[Connection]) -> String {
+    let mut s = format!(
+        "# minimum spanning tree, total length {}\n",
+        total_length(connections)
+    );
+    if let Some(last) = connections.last() {
+        s.push_str(&format!(
+            "# last cable {} - {}\n",
+            field.format_box(last.a),
+            field.format_box(last.b)
+        ));
+    }
+    for point in &field.positions {
+        let (x, y, z) = point.position;
+        s.push_str(&format!("v {} {} {}\n", x, y, z));
+    }
+    // vertices are numbered from 1, lines come in the order the cables were added
+    for c in connections {
+        s.push_str(&format!("l {} {}\n", c.a + 1, c.b + 1));
+    }
+    s
+}
+
+pub fn write_mst(
+    path: &str,
+    field: &JunctionField,
+    connections: &[Connection],
+    format: MstFormat,
+) -> io::Result<()> {
+    let content = match format {
+        MstFormat::Csv => csv(field, connections),
+        MstFormat::Dot => dot(field, connections),
+        MstFormat::Obj => obj(field, connections),
+    };
+    fs::write(path, content)
+}
@@ -1,14 +1,113 @@
-use std::{
-    collections::{HashMap, HashSet},
-    thread::current,
-};
+mod export;
 
+use clap::Parser;
+use export::MstFormat;
 use mt_logger::*;
-use utils::Point3D;
+use utils::{Point3D, input::Input, kdtree::KdTree};
+
+#[derive(Parser, Debug)]
+struct Args {
+    /// one junction box per line as x,y,z
+    filename: String,
+    /// how many of the closest pairs get connected for part 1
+    #[arg(long, default_value_t = 1000)]
+    connections: usize,
+    /// how many of the largest circuits get multiplied for part 1
+    #[arg(long, default_value_t = 3)]
+    top: usize,
+    /// print every circuit after the part 1 connections with its boxes
+    #[arg(long)]
+    report: bool,
+    /// write the minimum spanning tree of all boxes to this file
+    #[arg(long)]
+    mst: Option<String>,
+    #[arg(long, value_enum, default_value_t = MstFormat::Csv)]
+    mst_format: MstFormat,
+}
+
 struct JunctionField {
     positions: Vec<Point3D>,
-    connected_pairs: HashSet<(Point3D, Point3D)>,
-    num_boxes: usize,
+}
+
+/// A cable between the boxes `a` and `b`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Connection {
+    a: usize,
+    b: usize,
+    length: f64,
+}
+
+// union-find over the boxes, every box starts as its own circuit
+struct Circuits {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+    count: usize,
+}
+
+impl Circuits {
+    fn new(boxes: usize) -> Circuits {
+        Circuits {
+            parent: (0..boxes).collect(),
+            size: vec![1; boxes],
+            count: boxes,
+        }
+    }
+
+    fn find(&mut self, mut b: usize) -> usize {
+        while self.parent[b] != b {
+            self.parent[b] = self.parent[self.parent[b]];
+            b = self.parent[b];
+        }
+        b
+    }
+
+    // false if `a` and `b` already were in the same circuit
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        let (large, small) = if self.size[a] < self.size[b] {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        self.parent[small] = large;
+        self.size[large] += self.size[small];
+        self.count -= 1;
+        true
+    }
+
+    /// The boxes of every circuit, single boxes included, largest circuit
+    /// first and circuits of equal size by their lowest box.
+    fn members(&mut self) -> Vec<Vec<usize>> {
+        let mut members: Vec<Vec<usize>> = vec![Vec::new(); self.parent.len()];
+        for b in 0..self.parent.len() {
+            let root = self.find(b);
+            members[root].push(b);
+        }
+        members.retain(|m| !m.is_empty());
+        members.sort_by(|m, n| n.len().cmp(&m.len()).then(m[0].cmp(&n[0])));
+        members
+    }
+}
+
+/// What connecting the closest pairs of boxes one after another leads to.
+#[derive(Debug)]
+struct Network {
+    /// the circuits after the first `budget` pairs were connected
+    circuits: Vec<Vec<usize>>,
+    /// the connections that joined two circuits, in the order they were made,
+    /// which form a minimum spanning tree of the boxes
+    connections: Vec<Connection>,
+}
+
+impl Network {
+    /// The connection that joined the last two circuits into one, `None` for
+    /// fewer than two boxes.
+    fn last(&self) -> Option<Connection> {
+        self.connections.last().copied()
+    }
 }
 
 impl JunctionField {
@@ -25,186 +124,178 @@ impl JunctionField {
                 }
             })
             .collect();
-        JunctionField {
-            positions,
-            connected_pairs: HashSet::new(),
-            num_boxes: s.lines().count(),
-        }
+        JunctionField { positions }
     }
 
-    fn get_closest_boxes(&self) -> Vec<(Point3D, Point3D)> {
-        let mut pairs = Vec::new();
-        let pts = &self.positions;
-
-        for i in 0..pts.len() {
-            for j in (i + 1)..pts.len() {
-                pairs.push((pts[i], pts[j])); // if Point3D is Copy
+    /// Connects pairs of boxes closest first until all boxes form one
+    /// circuit, Kruskal style. Pairs already in the same circuit count
+    /// towards the `budget` but change nothing.
+    fn connect(&self, budget: usize) -> Network {
+        let tree = KdTree::new(&self.positions);
+        let mut circuits = Circuits::new(self.positions.len());
+        let mut after_budget = None;
+        let mut connections = Vec::new();
+        for (i, (a, b, length)) in tree.pairs().enumerate() {
+            if circuits.count == 1 {
+                break;
             }
-        }
-
-        pairs.sort_by(|pairA, pairB| {
-            pairA
-                .0
-                .distance(&pairA.1)
-                .total_cmp(&pairB.0.distance(&pairB.1))
-        });
-        mt_log!(Level::Debug, "{:?}", pairs);
-        pairs
-    }
-
-    fn part_1(&self) {
-        let mut circuits: Vec<Vec<Point3D>> = Vec::new();
-        let pairs = self.get_closest_boxes();
-
-        for pair in pairs.iter().take(1000) {
-            mt_log!(Level::Debug, "{:?}", pair);
-            //mt_log!(Level::Debug, "{:?}", circuits);
-            // check if any of the points are in any circuit
-            if let Some(index_a) = check_if_in_circuit(&pair.0, &circuits) {
-                if let Some(index_b) = check_if_in_circuit(&pair.1, &circuits) {
-                    mt_log!(Level::Debug, "Found circuit where pointA and pointB are in");
-                    if index_a != index_b {
-                        merge_circuit(&mut circuits, index_a, index_b);
-
-                        if circuits[0].len() == self.num_boxes {
-                            mt_log!(Level::Debug, "All Junction have been merged");
-                            break;
-                        }
-                    }
-                    continue;
-                }
-                circuits[index_a].push(pair.1);
-                if circuits[0].len() == self.num_boxes {
-                    mt_log!(Level::Debug, "All Junction have been merged");
-                    break;
-                }
-                continue;
+            if i == budget {
+                after_budget = Some(circuits.members());
             }
-
-            if let Some(index_a) = check_if_in_circuit(&pair.1, &circuits) {
-                if let Some(index_b) = check_if_in_circuit(&pair.0, &circuits) {
-                    if index_a != index_b {
-                        merge_circuit(&mut circuits, index_a, index_b);
-                        if circuits[0].len() == self.num_boxes {
-                            mt_log!(Level::Debug, "All Junction have been merged");
-                            break;
-                        }
-                    }
-                    continue;
-                }
-                circuits[index_a].push(pair.0);
-                if circuits[0].len() == self.num_boxes {
-                    mt_log!(Level::Debug, "All Junction have been merged");
-                    break;
-                }
-                continue;
+            mt_log!(Level::Debug, "{} {} {}", a, b, length);
+            if circuits.union(a, b) {
+                connections.push(Connection { a, b, length });
             }
-
-            circuits.push(vec![pair.0, pair.1]);
         }
-        let mut circuits_lens = circuits.iter().map(|c| c.len()).collect::<Vec<usize>>();
-        circuits_lens.sort();
-        circuits_lens.reverse();
-        let mut result = 1;
-        for circuit_len in circuits_lens.iter().take(3) {
-            result *= circuit_len;
+        Network {
+            circuits: after_budget.unwrap_or_else(|| circuits.members()),
+            connections,
         }
-        mt_log!(Level::Debug, "{:?}", circuits_lens);
-        mt_log!(Level::Info, "Result Part 1: {}", result);
-    }
-
-    fn part_2(&self) {
-        let mut circuits: Vec<Vec<Point3D>> = Vec::new();
-        let pairs = self.get_closest_boxes();
-        let mut result_part_2 = 0.0;
-        for pair in pairs.iter() {
-            mt_log!(Level::Debug, "{:?}", pair);
-            //mt_log!(Level::Debug, "{:?}", circuits);
-            // check if any of the points are in any circuit
-            if let Some(index_a) = check_if_in_circuit(&pair.0, &circuits) {
-                if let Some(index_b) = check_if_in_circuit(&pair.1, &circuits) {
-                    mt_log!(Level::Debug, "Found circuit where pointA and pointB are in");
-                    if index_a != index_b {
-                        merge_circuit(&mut circuits, index_a, index_b);
-
-                        if circuits[0].len() == self.num_boxes {
-                            result_part_2 = pair.0.position.0 * pair.1.position.0;
-                            mt_log!(Level::Debug, "All Junction have been merged");
-                            break;
-                        }
-                    }
-                    continue;
-                }
-                circuits[index_a].push(pair.1);
-                if circuits[0].len() == self.num_boxes {
-                    result_part_2 = pair.0.position.0 * pair.1.position.0;
-                    mt_log!(Level::Debug, "All Junction have been merged");
-                    break;
-                }
-                continue;
-            }
+    }
 
-            if let Some(index_a) = check_if_in_circuit(&pair.1, &circuits) {
-                if let Some(index_b) = check_if_in_circuit(&pair.0, &circuits) {
-                    if index_a != index_b {
-                        merge_circuit(&mut circuits, index_a, index_b);
-                        if circuits[0].len() == self.num_boxes {
-                            result_part_2 = pair.0.position.0 * pair.1.position.0;
-                            mt_log!(Level::Debug, "All Junction have been merged");
-                            break;
-                        }
-                    }
-                    continue;
-                }
-                circuits[index_a].push(pair.0);
-                if circuits[0].len() == self.num_boxes {
-                    result_part_2 = pair.0.position.0 * pair.1.position.0;
-                    mt_log!(Level::Debug, "All Junction have been merged");
-                    break;
-                }
-                continue;
-            }
+    fn part_1(&self, network: &Network, top: usize) -> usize {
+        network.circuits.iter().take(top).map(|c| c.len()).product()
+    }
 
-            circuits.push(vec![pair.0, pair.1]);
-        }
-        let mut circuits_lens = circuits.iter().map(|c| c.len()).collect::<Vec<usize>>();
-        circuits_lens.sort();
-        circuits_lens.reverse();
-        let mut result = 1;
-        for circuit_len in circuits_lens.iter().take(3) {
-            result *= circuit_len;
-        }
-        mt_log!(Level::Debug, "{:?}", circuits_lens);
+    fn part_2(&self, network: &Network) -> Option<f64> {
+        network
+            .last()
+            .map(|c| self.positions[c.a].position.0 * self.positions[c.b].position.0)
+    }
 
-        mt_log!(Level::Info, "Result Part 2: {}", result_part_2);
+    fn format_box(&self, b: usize) -> String {
+        let (x, y, z) = self.positions[b].position;
+        format!("{},{},{}", x, y, z)
     }
 }
-fn check_if_in_circuit(p: &Point3D, c: &[Vec<Point3D>]) -> Option<usize> {
-    for (i, circuit) in c.iter().enumerate() {
-        if circuit.contains(p) {
-            return Some(i);
+
+fn main() {
+    mt_new!(None, Level::Info, OutputStream::StdOut, true);
+    let args = Args::parse();
+    let filecontent = Input::read(&args.filename).expect("Could not read file");
+    let junction_field = JunctionField::from_string(filecontent.as_str());
+    let network = junction_field.connect(args.connections);
+
+    if args.report {
+        for (i, circuit) in network.circuits.iter().enumerate() {
+            let boxes: Vec<String> = circuit
+                .iter()
+                .map(|&b| junction_field.format_box(b))
+                .collect();
+            mt_log!(
+                Level::Info,
+                "Circuit {}: {} {}: {}",
+                i + 1,
+                circuit.len(),
+                if circuit.len() == 1 { "box" } else { "boxes" },
+                boxes.join(" ")
+            );
         }
     }
-    None
-}
+    mt_log!(
+        Level::Info,
+        "Result Part 1: {}",
+        junction_field.part_1(&network, args.top)
+    );
+    match junction_field.part_2(&network) {
+        Some(result) => mt_log!(Level::Info, "Result Part 2: {}", result),
+        None => mt_log!(Level::Error, "Part 2 needs at least two junction boxes"),
+    }
 
-fn merge_circuit(c: &mut Vec<Vec<Point3D>>, index_a: usize, index_b: usize) {
-    let circuit = c[index_a].clone();
-    for point in circuit {
-        c[index_b].push(point);
+    if let Some(path) = &args.mst {
+        mt_log!(
+            Level::Info,
+            "Minimum spanning tree: {} cables, total length {}",
+            network.connections.len(),
+            export::total_length(&network.connections)
+        );
+        if let Err(e) =
+            export::write_mst(path, &junction_field, &network.connections, args.mst_format)
+        {
+            mt_log!(Level::Error, "Could not write {}: {}", path, e);
+        }
     }
-    c.remove(index_a);
-}
-fn main() {
-    mt_new!(None, Level::Info, OutputStream::StdOut, true);
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() < 2 {
-        mt_log!(Level::Error, "Usage: {} <input.txt>", args[0]);
-    }
-    let filepath = &args[1];
-    let filecontent = std::fs::read_to_string(filepath).expect("Could not read file");
-    let junction_field = JunctionField::from_string(&filecontent);
-    junction_field.part_1();
-    junction_field.part_2();
     mt_flush!().unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "162,817,812
+57,618,57
+906,360,560
+592,479,940
+352,342,300
+466,668,158
+542,29,236
+431,825,988
+739,650,466
+52,470,668
+216,146,977
+819,987,18
+117,168,530
+805,96,715
+346,949,466
+970,615,88
+941,993,340
+862,61,35
+984,92,344
+425,690,689";
+
+    #[test]
+    fn solves_example() {
+        let junction_field = JunctionField::from_string(EXAMPLE);
+        let network = junction_field.connect(10);
+        assert_eq!(junction_field.part_1(&network, 3), 40);
+        assert_eq!(junction_field.part_2(&network), Some(25272.0));
+        assert_eq!(network.connections.len(), 19);
+    }
+
+    #[test]
+    fn spanning_tree_is_minimal() {
+        let junction_field = JunctionField::from_string(EXAMPLE);
+        let network = junction_field.connect(0);
+        // Prim's algorithm over all pairs as the reference
+        let points = &junction_field.positions;
+        let mut in_tree = vec![false; points.len()];
+        let mut best = vec![f64::INFINITY; points.len()];
+        best[0] = 0.0;
+        let mut total = 0.0;
+        for _ in 0..points.len() {
+            let next = (0..points.len())
+                .filter(|&p| !in_tree[p])
+                .min_by(|&p, &q| best[p].total_cmp(&best[q]))
+                .unwrap();
+            in_tree[next] = true;
+            total += best[next];
+            for p in 0..points.len() {
+                best[p] = best[p].min(points[next].distance(&points[p]));
+            }
+        }
+        let mst = export::total_length(&network.connections);
+        assert!((mst - total).abs() < 1e-6);
+        let last = network.last().unwrap();
+        assert_eq!(
+            (
+                junction_field.format_box(last.a),
+                junction_field.format_box(last.b)
+            ),
+            ("216,146,977".to_string(), "117,168,530".to_string())
+        );
+    }
+
+    #[test]
+    fn reports_single_boxes_as_circuits() {
+        let junction_field = JunctionField::from_string(EXAMPLE);
+        let network = junction_field.connect(10);
+        let sizes: Vec<usize> = network.circuits.iter().map(|c| c.len()).collect();
+        assert_eq!(sizes.iter().sum::<usize>(), 20);
+        assert_eq!(&sizes[..3], [5, 4, 2]);
+        assert_eq!(sizes.iter().filter(|&&s| s == 1).count(), 7);
+
+        let network = junction_field.connect(0);
+        assert_eq!(network.circuits.len(), 20);
+        assert_eq!(junction_field.part_1(&network, 3), 1);
+    }
+}
@@ -2,6 +2,7 @@ use indicatif::{ProgressBar, ProgressStyle};
 
 use mt_logger::*;
 use std::{collections::HashSet, hash::Hash};
+use utils::input::Input;
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 struct Point(isize, isize);
@@ -358,8 +359,8 @@ fn main() {
         mt_log!(Level::Error, "Usage: {} <input.txt>", args[0]);
     }
     let filepath = &args[1];
-    let filecontent = std::fs::read_to_string(filepath).expect("Could not read file");
-    let field = Field::from_string(&filecontent);
+    let filecontent = Input::read(filepath).expect("Could not read file");
+    let field = Field::from_string(filecontent.as_str());
     field.part1();
     field.part2_v2();
     mt_flush!().unwrap();
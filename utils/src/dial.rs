@@ -0,0 +1,119 @@
+/// A circular dial with the positions `left..=right`.
+///
+/// Rotating past `right` wraps around to `left` and vice versa. Positive
+/// rotations turn the dial towards `right` (R), negative ones towards `left` (L).
+/// The dial counts how often it passes its target position, which is 0 unless
+/// set with [`Dial::with_target`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Dial {
+    left: i64,
+    right: i64,
+    position: i64,
+    target: i64,
+}
+
+impl Dial {
+    /// Creates a dial over `left..=right` pointing at `start`.
+    ///
+    /// Panics if the range is empty or `start` lies outside of it.
+    pub fn new(left: i64, right: i64, start: i64) -> Dial {
+        assert!(left <= right, "Empty dial range {}..={}", left, right);
+        assert!(
+            (left..=right).contains(&start),
+            "Start {} is outside of {}..={}",
+            start,
+            left,
+            right
+        );
+        Dial {
+            left,
+            right,
+            position: start,
+            target: 0,
+        }
+    }
+
+    /// Counts passes of `target` instead of 0. A target outside of the dial
+    /// range is never passed.
+    pub fn with_target(mut self, target: i64) -> Dial {
+        self.target = target;
+        self
+    }
+
+    pub fn left(&self) -> i64 {
+        self.left
+    }
+
+    pub fn right(&self) -> i64 {
+        self.right
+    }
+
+    pub fn position(&self) -> i64 {
+        self.position
+    }
+
+    pub fn target(&self) -> i64 {
+        self.target
+    }
+
+    pub fn is_on_target(&self) -> bool {
+        self.position == self.target
+    }
+
+    /// Number of positions on the dial.
+    pub fn size(&self) -> i128 {
+        self.right as i128 - self.left as i128 + 1
+    }
+
+    /// Rotates the dial by `rotation` clicks in O(1).
+    ///
+    /// Returns the new position and how often the dial passed its target on
+    /// the way, i.e. the clicks before the last one that pointed at the target.
+    /// Whether the dial landed on the target can be read off the returned
+    /// position.
+    pub fn rotate(&mut self, rotation: i64) -> (i64, u64) {
+        let size = self.size();
+        let offset = self.position as i128 - self.left as i128;
+        let clicks = rotation.unsigned_abs() as i128;
+
+        let mut passes = 0;
+        if (self.left..=self.right).contains(&self.target) && clicks > 1 {
+            let target_offset = self.target as i128 - self.left as i128;
+            // first click (1..=size) that points at the target
+            let mut first = if rotation > 0 {
+                (target_offset - offset).rem_euclid(size)
+            } else {
+                (offset - target_offset).rem_euclid(size)
+            };
+            if first == 0 {
+                first = size;
+            }
+            if first < clicks {
+                passes = ((clicks - 1 - first) / size + 1) as u64;
+            }
+        }
+
+        let offset = (offset + rotation as i128).rem_euclid(size);
+        self.position = (self.left as i128 + offset) as i64;
+        (self.position, passes)
+    }
+
+    /// Same as [`Dial::rotate`] but walks the dial click by click in O(|rotation|).
+    pub fn rotate_stepwise(&mut self, rotation: i64) -> (i64, u64) {
+        let direction = rotation.signum();
+        let mut passes = 0;
+        for click in 0..rotation.unsigned_abs() {
+            if click > 0 && self.position == self.target {
+                passes += 1;
+            }
+            if direction < 0 && self.position == self.left {
+                self.position = self.right;
+            } else if direction > 0 && self.position == self.right {
+                self.position = self.left;
+            } else {
+                self.position += direction;
+            }
+        }
+        (self.position, passes)
+    }
+}
@@ -0,0 +1,74 @@
+use std::{fs, io, path::Path};
+
+/// Puzzle input with normalised line endings.
+///
+/// Loading strips a leading BOM, turns `\r\n` and lone `\r` into `\n` and
+/// removes trailing newlines, so the days never have to care about how the
+/// file was saved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Input {
+    content: String,
+}
+
+impl Input {
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Input> {
+        Ok(Input::from_string(&fs::read_to_string(path)?))
+    }
+
+    pub fn from_string(s: &str) -> Input {
+        let s = s.strip_prefix('\u{feff}').unwrap_or(s);
+        let content = s.replace("\r\n", "\n").replace('\r', "\n");
+        let content = content.trim_end_matches('\n').to_string();
+        Input { content }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.content
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.content.lines()
+    }
+
+    /// Blocks separated by one or more blank (or whitespace only) lines.
+    pub fn sections(&self) -> Vec<&str> {
+        let mut sections = Vec::new();
+        let mut start = None;
+        let mut end = 0;
+        let mut offset = 0;
+        for line in self.content.split('\n') {
+            if line.trim().is_empty() {
+                if let Some(s) = start.take() {
+                    sections.push(&self.content[s..end]);
+                }
+            } else {
+                start.get_or_insert(offset);
+                end = offset + line.len();
+            }
+            offset += line.len() + 1;
+        }
+        if let Some(s) = start {
+            sections.push(&self.content[s..end]);
+        }
+        sections
+    }
+
+    /// The input as rows of characters, all of the same length.
+    pub fn grid(&self) -> Result<Vec<Vec<char>>, String> {
+        let rows: Vec<Vec<char>> = self.lines().map(|line| line.chars().collect()).collect();
+        let Some(width) = rows.first().map(|row| row.len()) else {
+            return Err("Grid is empty".to_string());
+        };
+        for (i, row) in rows.iter().enumerate() {
+            if row.len() != width {
+                return Err(format!(
+                    "Grid is not rectangular: line {} has {} columns, expected {}",
+                    i + 1,
+                    row.len(),
+                    width
+                ));
+            }
+        }
+        Ok(rows)
+    }
+}
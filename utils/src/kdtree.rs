@@ -0,0 +1,217 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+};
+
+use crate::Point3D;
+
+// a point and its squared distance to the one a query is about, ordered by
+// distance and then by index so that ties always come out the same way
+#[derive(Clone, Copy, Debug)]
+struct Candidate {
+    distance_squared: f64,
+    point: usize,
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance_squared
+            .total_cmp(&other.distance_squared)
+            .then(self.point.cmp(&other.point))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+fn coordinates(point: &Point3D) -> [f64; 3] {
+    [point.position.0, point.position.1, point.position.2]
+}
+
+fn distance_squared(a: [f64; 3], b: [f64; 3]) -> f64 {
+    (0..3).map(|axis| (a[axis] - b[axis]).powi(2)).sum()
+}
+
+/// A k-d tree over 3D points for nearest neighbour queries.
+///
+/// Points are referred to by their index in the slice the tree was built
+/// from.
+#[derive(Clone, Debug)]
+pub struct KdTree {
+    points: Vec<[f64; 3]>,
+    // point indices, every range `lo..hi` has its splitting point in the
+    // middle, smaller coordinates on the axis `depth % 3` left of it
+    order: Vec<usize>,
+}
+
+impl KdTree {
+    pub fn new(points: &[Point3D]) -> KdTree {
+        let mut tree = KdTree {
+            points: points.iter().map(coordinates).collect(),
+            order: (0..points.len()).collect(),
+        };
+        tree.build(0, points.len(), 0);
+        tree
+    }
+
+    fn build(&mut self, lo: usize, hi: usize, depth: usize) {
+        if hi - lo <= 1 {
+            return;
+        }
+        let axis = depth % 3;
+        let mid = (lo + hi) / 2;
+        let points = &self.points;
+        self.order[lo..hi].select_nth_unstable_by(mid - lo, |&a, &b| {
+            points[a][axis].total_cmp(&points[b][axis])
+        });
+        self.build(lo, mid, depth + 1);
+        self.build(mid + 1, hi, depth + 1);
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// The `k` points closest to `point` other than itself with their
+    /// squared distances, closest first. Equally distant points come in
+    /// index order.
+    pub fn nearest(&self, point: usize, k: usize) -> Vec<(usize, f64)> {
+        let mut best = BinaryHeap::with_capacity(k + 1);
+        if k > 0 {
+            self.search(point, k, 0, self.len(), 0, &mut best);
+        }
+        best.into_sorted_vec()
+            .into_iter()
+            .map(|c| (c.point, c.distance_squared))
+            .collect()
+    }
+
+    fn search(
+        &self,
+        point: usize,
+        k: usize,
+        lo: usize,
+        hi: usize,
+        depth: usize,
+        best: &mut BinaryHeap<Candidate>,
+    ) {
+        if lo >= hi {
+            return;
+        }
+        let mid = (lo + hi) / 2;
+        let here = self.order[mid];
+        let target = self.points[point];
+        if here != point {
+            best.push(Candidate {
+                distance_squared: distance_squared(target, self.points[here]),
+                point: here,
+            });
+            if best.len() > k {
+                best.pop();
+            }
+        }
+        let axis = depth % 3;
+        let offset = target[axis] - self.points[here][axis];
+        let (near, far) = if offset < 0.0 {
+            ((lo, mid), (mid + 1, hi))
+        } else {
+            ((mid + 1, hi), (lo, mid))
+        };
+        self.search(point, k, near.0, near.1, depth + 1, best);
+        // an equally distant point on the other side may still win on its index
+        if best.len() < k || offset.powi(2) <= best.peek().unwrap().distance_squared {
+            self.search(point, k, far.0, far.1, depth + 1, best);
+        }
+    }
+
+    /// Every pair of points, closest first, as `(a, b, distance)` with `a < b`.
+    ///
+    /// Pairs are produced lazily: every point keeps a batch of its nearest
+    /// neighbours and a heap holds the next neighbour of every point, so
+    /// taking the first `m` pairs of `n` points needs neither all
+    /// `n * (n - 1) / 2` pairs nor a sort of them.
+    pub fn pairs(&self) -> Pairs<'_> {
+        let mut pairs = Pairs {
+            tree: self,
+            neighbours: vec![Vec::new(); self.len()],
+            consumed: vec![0; self.len()],
+            heap: BinaryHeap::with_capacity(self.len()),
+        };
+        for point in 0..self.len() {
+            pairs.advance(point);
+        }
+        pairs
+    }
+}
+
+const FIRST_BATCH: usize = 8;
+
+/// Iterator over the pairs of a [`KdTree`] in increasing distance.
+#[derive(Debug)]
+pub struct Pairs<'a> {
+    tree: &'a KdTree,
+    // the nearest neighbours of every point fetched so far
+    neighbours: Vec<Vec<(usize, f64)>>,
+    // how many of them already went through the heap
+    consumed: Vec<usize>,
+    // the next neighbour of every point that has one left, closest on top
+    heap: BinaryHeap<Reverse<(Candidate, usize)>>,
+}
+
+impl Pairs<'_> {
+    // pushes the next neighbour of `point`, fetching twice as many
+    // neighbours as before once the batch runs out
+    fn advance(&mut self, point: usize) {
+        let consumed = self.consumed[point];
+        let fetched = self.neighbours[point].len();
+        if consumed == fetched {
+            let total = self.tree.len() - 1;
+            if fetched == total {
+                self.neighbours[point] = Vec::new();
+                return;
+            }
+            let k = (fetched * 2).max(FIRST_BATCH).min(total);
+            self.neighbours[point] = self.tree.nearest(point, k);
+        }
+        let (neighbour, distance_squared) = self.neighbours[point][consumed];
+        self.consumed[point] += 1;
+        self.heap.push(Reverse((
+            Candidate {
+                distance_squared,
+                point: neighbour,
+            },
+            point,
+        )));
+    }
+}
+
+impl Iterator for Pairs<'_> {
+    type Item = (usize, usize, f64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // every pair shows up once for each of its points, the lower one
+        // reports it
+        while let Some(Reverse((candidate, point))) = self.heap.pop() {
+            self.advance(point);
+            if point < candidate.point {
+                return Some((point, candidate.point, candidate.distance_squared.sqrt()));
+            }
+        }
+        None
+    }
+}
@@ -1,15 +1,8 @@
-pub fn get_rotations(number: i32, rotation: i32, left: i32, right: i32) -> i32 {
-    // expects left to be 0 and the counting number to be 0 as well
-    let interval = right - left + 1;
-    let mut rotations = rotation.abs() / interval;
-    let rotation = rotation % interval;
-    let position = number + rotation;
-    let number_was_zero = (number == 0) as i32;
-    if position < 0 || position > (right + 1) {
-        rotations += 1 - number_was_zero;
-    }
-    rotations
-}
+mod dial;
+pub mod input;
+pub mod kdtree;
+
+pub use dial::Dial;
 
 pub fn get_rotations_number(number: i32, rotation: i32, left: i32, right: i32) -> (i32, i32) {
     let mut position = number;
@@ -35,16 +28,6 @@ pub fn get_rotations_number(number: i32, rotation: i32, left: i32, right: i32) -
     (position, number_was_zero)
 }
 
-pub fn map_number(number: i32, left: i32, right: i32) -> i32 {
-    let mut position = number;
-    let interval = right - left + 1;
-    position %= interval;
-    if position < left {
-        position += interval;
-    }
-    position
-}
-
 pub fn filter_non_relevant_chars(s: &str) -> Vec<String> {
     let mut result: Vec<String> = Vec::new();
     let mut current_s: Vec<char> = Vec::new();
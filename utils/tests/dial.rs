@@ -0,0 +1,124 @@
+use proptest::prelude::*;
+use std::ops::RangeInclusive;
+use utils::{Dial, get_rotations_number};
+
+fn dial_and_rotations() -> impl Strategy<Value = (i32, i32, i32, Vec<i32>)> {
+    dial_from(-300..=300, 1..=250)
+}
+
+fn dial_from(
+    lefts: RangeInclusive<i32>,
+    sizes: RangeInclusive<i32>,
+) -> impl Strategy<Value = (i32, i32, i32, Vec<i32>)> {
+    (lefts, sizes).prop_flat_map(|(left, size)| {
+        let right = left + size - 1;
+        (
+            Just(left),
+            Just(right),
+            left..=right,
+            prop::collection::vec(
+                prop_oneof![
+                    -100_000i32..=-1,
+                    1i32..=100_000,
+                    -1_000i32..=-1,
+                    1i32..=1_000
+                ],
+                1..20,
+            ),
+        )
+    })
+}
+
+proptest! {
+    #[test]
+    // the walker counts the clicks that land on `left`, which is the target 0 here, but
+    // misses the clicks of a single position dial that wrap onto it
+    fn rotate_matches_step_by_step_walker(
+        (left, right, start, rotations) in dial_from(0..=0, 2..=250),
+    ) {
+        let mut dial = Dial::new(left as i64, right as i64, start as i64);
+        let mut position = start;
+        for rotation in rotations {
+            let (walked_position, walked_zeros) = get_rotations_number(position, rotation, left, right);
+            let (new_position, passes) = dial.rotate(rotation as i64);
+            let landed = (new_position == 0) as u64;
+            prop_assert_eq!(new_position, walked_position as i64);
+            prop_assert_eq!(passes + landed, walked_zeros as u64);
+            position = walked_position;
+        }
+    }
+
+    #[test]
+    fn rotate_huge_values_splits_consistently(
+        left in -1_000_000_000i64..=1_000_000_000,
+        size in 1i64..=1_000_000_000,
+        start_offset in 0i64..1_000_000_000,
+        a in 1i64..=i64::MAX / 2,
+        b in 1i64..=i64::MAX / 2,
+        negative in any::<bool>(),
+    ) {
+        let right = left + size - 1;
+        let start = left + start_offset % size;
+        let (a, b) = if negative { (-a, -b) } else { (a, b) };
+
+        let mut whole = Dial::new(left, right, start);
+        let (whole_position, whole_passes) = whole.rotate(a + b);
+
+        let mut split = Dial::new(left, right, start);
+        let (middle, first_passes) = split.rotate(a);
+        let (split_position, second_passes) = split.rotate(b);
+
+        prop_assert_eq!(whole_position, split_position);
+        prop_assert_eq!(whole_passes, first_passes + (middle == 0) as u64 + second_passes);
+    }
+}
+
+#[test]
+fn rotate_full_turns_from_zero() {
+    let mut dial = Dial::new(0, 99, 0);
+    assert_eq!(dial.rotate(100), (0, 0));
+    assert_eq!(dial.rotate(-1000), (0, 9));
+    assert_eq!(dial.rotate(150), (50, 1));
+}
+
+#[test]
+fn rotate_extreme_values() {
+    let mut dial = Dial::new(0, 99, 50);
+    assert_eq!(dial.rotate(i64::MAX), (57, 92233720368547758));
+    let mut dial = Dial::new(i64::MIN, i64::MAX, 0);
+    assert_eq!(dial.rotate(i64::MIN), (i64::MIN, 0));
+    assert_eq!(dial.rotate(-1), (i64::MAX, 0));
+    assert_eq!(dial.rotate(1), (i64::MIN, 0));
+}
+
+#[test]
+fn rotate_without_zero_on_dial() {
+    let mut dial = Dial::new(-100, -1, -50);
+    assert_eq!(dial.rotate(1_000_000), (-50, 0));
+    let mut dial = Dial::new(5, 5, 5);
+    assert_eq!(dial.rotate(-7), (5, 0));
+}
+
+#[test]
+fn rotate_counts_custom_target() {
+    let mut dial = Dial::new(1, 12, 12).with_target(3);
+    assert_eq!(dial.rotate(27), (3, 2));
+    assert!(dial.is_on_target());
+    assert_eq!(dial.rotate(-13), (2, 1));
+    let mut dial = Dial::new(0, 9, 0).with_target(10);
+    assert_eq!(dial.rotate(100), (0, 0));
+}
+
+proptest! {
+    #[test]
+    fn rotate_matches_rotate_stepwise(
+        (left, right, start, rotations) in dial_and_rotations(),
+        target_shift in -5i32..=300,
+    ) {
+        let dial = Dial::new(left as i64, right as i64, start as i64).with_target((left + target_shift) as i64);
+        let (mut closed_form, mut stepwise) = (dial, dial);
+        for rotation in rotations {
+            prop_assert_eq!(closed_form.rotate(rotation as i64), stepwise.rotate_stepwise(rotation as i64));
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use utils::input::Input;
+
+#[test]
+fn normalises_line_endings_and_bom() {
+    let crlf = Input::from_string("\u{feff}a\r\nb\r\n\r\nc\r\n");
+    let lf = Input::from_string("a\nb\n\nc\n");
+    assert_eq!(crlf, lf);
+    assert_eq!(lf.as_str(), "a\nb\n\nc");
+    assert_eq!(
+        Input::from_string("a\rb\r").lines().collect::<Vec<_>>(),
+        ["a", "b"]
+    );
+}
+
+#[test]
+fn splits_sections_on_blank_lines() {
+    let input = Input::from_string("\n1-3\n5-8\n\n  \n\n7\n9\n\n");
+    assert_eq!(input.sections(), ["1-3\n5-8", "7\n9"]);
+    assert!(Input::from_string("\n\n").sections().is_empty());
+}
+
+#[test]
+fn checks_rectangular_grids() {
+    let input = Input::from_string("..@\n@.@\r\n");
+    assert_eq!(input.grid().unwrap().len(), 2);
+    let ragged = Input::from_string("..@\n@.@ \n");
+    assert_eq!(
+        ragged.grid().unwrap_err(),
+        "Grid is not rectangular: line 2 has 4 columns, expected 3"
+    );
+    assert!(Input::from_string("").grid().is_err());
+}
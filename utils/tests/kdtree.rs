@@ -0,0 +1,80 @@
+use proptest::prelude::*;
+use utils::{Point3D, kdtree::KdTree};
+
+fn points() -> impl Strategy<Value = Vec<Point3D>> {
+    // small coordinates so that equal distances are common
+    prop::collection::vec((0i32..6, 0i32..6, 0i32..6), 0..40).prop_map(|coords| {
+        coords
+            .into_iter()
+            .map(|(x, y, z)| Point3D {
+                position: (x as f64, y as f64, z as f64),
+            })
+            .collect()
+    })
+}
+
+fn all_pairs(points: &[Point3D]) -> Vec<(usize, usize, f64)> {
+    let mut pairs = Vec::new();
+    for a in 0..points.len() {
+        for b in a + 1..points.len() {
+            pairs.push((a, b, points[a].distance(&points[b])));
+        }
+    }
+    pairs.sort_by(|p, q| p.2.total_cmp(&q.2));
+    pairs
+}
+
+proptest! {
+    #[test]
+    fn pairs_come_in_increasing_distance(points in points()) {
+        let tree = KdTree::new(&points);
+        let pairs: Vec<(usize, usize, f64)> = tree.pairs().collect();
+        let expected = all_pairs(&points);
+        prop_assert_eq!(pairs.len(), expected.len());
+        for (pair, expected) in pairs.iter().zip(&expected) {
+            prop_assert!((pair.2 - expected.2).abs() < 1e-9);
+            prop_assert!((pair.2 - points[pair.0].distance(&points[pair.1])).abs() < 1e-9);
+        }
+        let mut seen: Vec<(usize, usize)> = pairs.iter().map(|&(a, b, _)| (a, b)).collect();
+        seen.sort();
+        seen.dedup();
+        prop_assert_eq!(seen.len(), expected.len());
+    }
+
+    #[test]
+    fn nearest_matches_brute_force(points in points(), k in 0usize..12) {
+        let tree = KdTree::new(&points);
+        for point in 0..points.len() {
+            let mut expected: Vec<(f64, usize)> = (0..points.len())
+                .filter(|&other| other != point)
+                .map(|other| (points[point].distance(&points[other]).powi(2), other))
+                .collect();
+            expected.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+            expected.truncate(k);
+            let nearest: Vec<(f64, usize)> = tree
+                .nearest(point, k)
+                .into_iter()
+                .map(|(other, d)| (d, other))
+                .collect();
+            prop_assert_eq!(nearest.iter().map(|n| n.1).collect::<Vec<_>>(),
+                expected.iter().map(|e| e.1).collect::<Vec<_>>());
+        }
+    }
+}
+
+#[test]
+fn takes_the_closest_pairs_first() {
+    let points: Vec<Point3D> = [
+        (0.0, 0.0, 0.0),
+        (10.0, 0.0, 0.0),
+        (1.0, 0.0, 0.0),
+        (10.0, 2.0, 0.0),
+    ]
+    .into_iter()
+    .map(|position| Point3D { position })
+    .collect();
+    let tree = KdTree::new(&points);
+    let first: Vec<(usize, usize, f64)> = tree.pairs().take(2).collect();
+    assert_eq!(first, [(0, 2, 1.0), (1, 3, 2.0)]);
+    assert_eq!(KdTree::new(&points[..1]).pairs().count(), 0);
+}